@@ -0,0 +1,269 @@
+//! Contract call helpers
+//!
+//! Dry-runs contract calls through the `ContractsApi_call` runtime API and
+//! decodes the contract's typed `Error` so failures can be explained before
+//! a transaction is submitted.
+
+use anyhow::{anyhow, bail, Context, Result};
+use codec::{Decode, Encode};
+use subxt::OnlineClient;
+
+use crate::TheseusConfig;
+
+/// Mirror of the contract's `Error` enum
+///
+/// Variant order must match `prediction_market::Error` exactly, since errors
+/// are decoded by variant index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Decode)]
+pub enum ContractError {
+    UnknownSelector,
+    InvalidArguments,
+    NotAuthorized,
+    MarketCreatorNotConfigured,
+    ResolverNotConfigured,
+    TooFewOptions,
+    TooManyOptions,
+    MarketNotFound,
+    MarketNotOpen,
+    InvalidOptionIndex,
+    DeadlineNotReached,
+    // Reserved: no longer returned, kept so later variants keep their index
+    OracleFailed,
+    InvalidResolutionResult,
+    MarketNotPendingResolution,
+    MarketNotResolved,
+    NoPosition,
+    NoWinningShares,
+    WinningPoolEmpty,
+    RequestIdMismatch,
+    RequestAlreadyProcessed,
+    AgentRequestFailed,
+    AgentNotFound,
+    AgentRequestRejected,
+    InsufficientAgentFunds,
+    StakeMismatch,
+    ZeroAmount,
+    NotPayable,
+    TransferFailed,
+    ResolutionInProgress,
+    MarketNotVoided,
+    InvalidConfig,
+    TreasuryNotConfigured,
+    NotTreasuryPool,
+    BettingClosed,
+    FeeTooHigh,
+    NoFeesAccrued,
+    MarketNotProposed,
+    DisputeWindowClosed,
+    DisputeWindowOpen,
+    AlreadyDisputed,
+    MarketNotDisputed,
+    PoolNotEmpty,
+    Overflow,
+    PricingMismatch,
+    InsufficientShares,
+    SlippageExceeded,
+    InsufficientAllowance,
+    InvalidRecipient,
+    BetTooSmall,
+    BetTooLarge,
+    PositionLimitExceeded,
+    ContractPaused,
+    ResolutionEscalated,
+    ResolutionAttemptsExhausted,
+}
+
+impl ContractError {
+    /// Human-readable explanation of the error
+    pub fn explain(&self) -> &'static str {
+        match self {
+            Self::UnknownSelector => "The contract does not recognise this call. Is PM_CONTRACT pointing at a prediction market?",
+            Self::InvalidArguments => "The contract could not decode the call arguments.",
+            Self::NotAuthorized => "Your account is not allowed to perform this action (it may need the admin or a role such as config manager).",
+            Self::MarketCreatorNotConfigured => "The contract has no Market Creator agent configured yet.",
+            Self::ResolverNotConfigured => "The contract has no Resolver Oracle agent configured yet.",
+            Self::TooFewOptions => "A market needs at least 2 options.",
+            Self::TooManyOptions => "A market can have at most 10 options.",
+            Self::MarketNotFound => "No market exists with this ID.",
+            Self::MarketNotOpen => "The market is no longer open (resolution may already be pending or complete).",
+            Self::InvalidOptionIndex => "The option index is out of range for this market.",
+            Self::DeadlineNotReached => "The market's resolution deadline has not been reached yet.",
+            Self::OracleFailed => "The Resolver Oracle failed to resolve the market.",
+            Self::InvalidResolutionResult => "The Resolver Oracle returned a result the contract could not decode.",
+            Self::MarketNotPendingResolution => "The market is not waiting for a resolution.",
+            Self::MarketNotResolved => "The market has not been resolved yet.",
+            Self::NoPosition => "You have no position in this market (or it was already claimed).",
+            Self::NoWinningShares => "You hold no shares in the winning option.",
+            Self::WinningPoolEmpty => "Nobody bet on the winning option. Depending on the contract's policy, reclaim your stake with claim_refund or the pool goes to the treasury.",
+            Self::RequestIdMismatch => "The oracle callback does not match the market's pending resolution request.",
+            Self::RequestAlreadyProcessed => "This oracle callback has already been processed.",
+            Self::AgentRequestFailed => "The chain extension did not accept the request to the Resolver Oracle. Try again later.",
+            Self::AgentNotFound => "The configured Resolver Oracle is not a registered agent.",
+            Self::AgentRequestRejected => "The Resolver Oracle does not accept requests from this contract.",
+            Self::InsufficientAgentFunds => "The contract cannot pay for a Resolver Oracle run. Fund the contract account.",
+            Self::StakeMismatch => "The value sent does not match the amount required (bet stake, dispute bond or LMSR subsidy).",
+            Self::ZeroAmount => "The amount must be greater than zero (and, for LMSR trades, large enough to price).",
+            Self::NotPayable => "This call does not accept a value transfer.",
+            Self::TransferFailed => "The contract could not transfer the payout.",
+            Self::ResolutionInProgress => "A Resolver Oracle run for this market is still in progress. Wait for it to finish or time out.",
+            Self::MarketNotVoided => "The market is not refunding stakes (it was not voided or cancelled, and its pool is not being refunded).",
+            Self::InvalidConfig => "The setting is out of range.",
+            Self::TreasuryNotConfigured => "The contract has no treasury account configured yet.",
+            Self::NotTreasuryPool => "This market's pool does not go to the treasury, or was already swept.",
+            Self::BettingClosed => "Betting on this market has closed.",
+            Self::FeeTooHigh => "The fee is above the maximum (10% protocol fee, 5% creator fee, 10% exit penalty).",
+            Self::NoFeesAccrued => "There are no accrued fees to withdraw.",
+            Self::MarketNotProposed => "The market has no proposed outcome waiting out its dispute window.",
+            Self::DisputeWindowClosed => "The dispute window for this outcome has closed. Call finalize_resolution to settle the market.",
+            Self::DisputeWindowOpen => "The proposed outcome can still be disputed. Wait for the dispute window to close.",
+            Self::AlreadyDisputed => "The proposed outcome has already been disputed.",
+            Self::MarketNotDisputed => "The market is not waiting for a dispute to be settled.",
+            Self::PoolNotEmpty => "The market already has bets, so only the admin or arbitrator can cancel it.",
+            Self::Overflow => "The amount is too large: the market's pool or the contract's escrow would overflow.",
+            Self::PricingMismatch => "This call does not apply to the market's pricing mode (place_bet is for parimutuel markets, buy_shares/sell_shares for LMSR markets).",
+            Self::InsufficientShares => "You hold fewer shares of this option than the trade needs.",
+            Self::SlippageExceeded => "The price moved: the trade costs more than the value sent, or pays less than your minimum. Get a fresh quote.",
+            Self::InsufficientAllowance => "The position's owner has not approved you to transfer that many shares.",
+            Self::InvalidRecipient => "Shares cannot be transferred to, or approved for, the account they come from.",
+            Self::BetTooSmall => "The bet is below the market's minimum bet.",
+            Self::BetTooLarge => "The bet is above the market's maximum bet.",
+            Self::PositionLimitExceeded => "The bet or transfer would take the account's total stake in this market over the per-account cap.",
+            Self::ContractPaused => "New markets, bets and share purchases are paused. Withdrawals, sales and claims still work.",
+            Self::ResolutionEscalated => "The market's resolution was escalated. Only the admin can settle it now.",
+            Self::ResolutionAttemptsExhausted => "The market has used all its resolution rounds. Only the admin can settle it now.",
+        }
+    }
+}
+
+impl std::fmt::Display for ContractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self, self.explain())
+    }
+}
+
+impl std::error::Error for ContractError {}
+
+/// Decode the error from a reverted call's output (`Result::Err` encoding)
+pub fn decode_error(output: &[u8]) -> Option<ContractError> {
+    match output.split_first() {
+        Some((1, rest)) => ContractError::decode(&mut &rest[..]).ok(),
+        _ => None,
+    }
+}
+
+/// Weight as encoded by the runtime
+#[derive(Decode, Encode)]
+struct Weight {
+    #[codec(compact)]
+    ref_time: u64,
+    #[codec(compact)]
+    proof_size: u64,
+}
+
+#[derive(Decode)]
+#[allow(dead_code)]
+enum StorageDeposit {
+    Refund(u128),
+    Charge(u128),
+}
+
+/// Output of a successful contract execution
+#[derive(Decode)]
+struct ExecReturnValue {
+    flags: u32,
+    data: Vec<u8>,
+}
+
+/// Only the variant index of the runtime's `DispatchError` is needed
+#[derive(Decode)]
+enum ExecResult {
+    Ok(ExecReturnValue),
+    Err,
+}
+
+/// Leading fields of `ContractExecResult`; trailing events are not decoded
+#[derive(Decode)]
+#[allow(dead_code)]
+struct ContractExecResult {
+    gas_consumed: Weight,
+    gas_required: Weight,
+    storage_deposit: StorageDeposit,
+    debug_message: Vec<u8>,
+    result: ExecResult,
+}
+
+/// Return flag set by the contract when it reverts
+const FLAG_REVERT: u32 = 1;
+
+/// Outcome of a dry-run
+pub struct DryRun {
+    /// Whether the contract reverted
+    pub reverted: bool,
+    /// SCALE-encoded contract output
+    pub data: Vec<u8>,
+}
+
+/// Execute a contract call against the latest block without submitting it
+pub async fn dry_run(
+    api: &OnlineClient<TheseusConfig>,
+    origin: [u8; 32],
+    contract: [u8; 32],
+    value: u128,
+    call_data: &[u8],
+) -> Result<DryRun> {
+    let gas_limit: Option<Weight> = None;
+    let storage_deposit_limit: Option<u128> = None;
+    let params = (origin, contract, value, gas_limit, storage_deposit_limit, call_data.to_vec()).encode();
+
+    let result: ContractExecResult = api
+        .runtime_api()
+        .at_latest()
+        .await
+        .context("fetching latest block")?
+        .call_raw("ContractsApi_call", Some(&params))
+        .await
+        .context("dry-running contract call")?;
+
+    match result.result {
+        ExecResult::Ok(ret) => Ok(DryRun {
+            reverted: ret.flags & FLAG_REVERT != 0,
+            data: ret.data,
+        }),
+        ExecResult::Err => Err(anyhow!(
+            "contract call failed in the runtime: {}",
+            String::from_utf8_lossy(&result.debug_message)
+        )),
+    }
+}
+
+/// Dry-run a call and fail with the contract's explanation if it would revert
+pub async fn ensure_call_succeeds(
+    api: &OnlineClient<TheseusConfig>,
+    origin: [u8; 32],
+    contract: [u8; 32],
+    value: u128,
+    call_data: &[u8],
+) -> Result<()> {
+    let outcome = dry_run(api, origin, contract, value, call_data).await?;
+    if outcome.reverted {
+        match decode_error(&outcome.data) {
+            Some(err) => return Err(err.into()),
+            None => bail!("contract reverted with undecodable output 0x{}", hex::encode(&outcome.data)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_error_matches_contract_indices() {
+        // `Err` tag followed by the variant index
+        assert_eq!(decode_error(&[1, 0]), Some(ContractError::UnknownSelector));
+        assert_eq!(decode_error(&[1, 53]), Some(ContractError::ResolutionAttemptsExhausted));
+        assert_eq!(decode_error(&[1, 54]), None);
+        assert_eq!(decode_error(&[0]), None);
+    }
+}
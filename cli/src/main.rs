@@ -11,12 +11,14 @@ use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
 use subxt::{dynamic::Value, OnlineClient, PolkadotConfig};
 use subxt_signer::sr25519::Keypair;
 
+mod contract;
+
 static CRYSTAL_BALL: Emoji<'_, '_> = Emoji("🔮 ", "");
 static CHECK: Emoji<'_, '_> = Emoji("✅ ", "[OK] ");
 static MONEY: Emoji<'_, '_> = Emoji("💰 ", "$");
 static CLOCK: Emoji<'_, '_> = Emoji("⏰ ", "");
 
-type TheseusConfig = PolkadotConfig;
+pub(crate) type TheseusConfig = PolkadotConfig;
 
 #[derive(Parser)]
 #[command(name = "pm")]
@@ -277,7 +279,7 @@ async fn create_market(cli: &Cli, question: Option<String>) -> Result<()> {
         "Agents",
         "run_agent",
         vec![
-            Value::from_bytes(&agent_id),
+            Value::from_bytes(agent_id),
             Value::from_bytes(&input_bytes),
         ],
     );
@@ -303,32 +305,30 @@ async fn create_market(cli: &Cli, question: Option<String>) -> Result<()> {
     let mut is_waiting = false;
     let mut is_complete = false;
 
-    for event in events.iter() {
-        if let Ok(ev) = event {
-            match (ev.pallet_name(), ev.variant_name()) {
-                ("Agents", "AgentCallQueued") => {
-                    // Try to extract run_id from event (first field is u64)
+    for ev in events.iter().flatten() {
+        match (ev.pallet_name(), ev.variant_name()) {
+            ("Agents", "AgentCallQueued") => {
+                // Try to extract run_id from event (first field is u64)
+                let bytes = ev.field_bytes();
+                if bytes.len() >= 8 {
+                    run_id = Some(u64::from_le_bytes(bytes[0..8].try_into().unwrap_or([0; 8])));
+                }
+                println!("  Agent run queued (run_id: {:?})", run_id);
+            }
+            ("Agents", "AgentRunWaitingForInput") => {
+                is_waiting = true;
+                // Extract run_id if not already set
+                if run_id.is_none() {
                     let bytes = ev.field_bytes();
                     if bytes.len() >= 8 {
                         run_id = Some(u64::from_le_bytes(bytes[0..8].try_into().unwrap_or([0; 8])));
                     }
-                    println!("  Agent run queued (run_id: {:?})", run_id);
-                }
-                ("Agents", "AgentRunWaitingForInput") => {
-                    is_waiting = true;
-                    // Extract run_id if not already set
-                    if run_id.is_none() {
-                        let bytes = ev.field_bytes();
-                        if bytes.len() >= 8 {
-                            run_id = Some(u64::from_le_bytes(bytes[0..8].try_into().unwrap_or([0; 8])));
-                        }
-                    }
-                }
-                ("Agents", "AgentCallCompleted") => {
-                    is_complete = true;
                 }
-                _ => {}
             }
+            ("Agents", "AgentCallCompleted") => {
+                is_complete = true;
+            }
+            _ => {}
         }
     }
 
@@ -381,27 +381,25 @@ async fn create_market(cli: &Cli, question: Option<String>) -> Result<()> {
 
                 // Check if agent completed or needs more input
                 let mut still_waiting = false;
-                for event in resume_events.iter() {
-                    if let Ok(ev) = event {
-                        match (ev.pallet_name(), ev.variant_name()) {
-                            ("Agents", "AgentRunWaitingForInput") => {
-                                still_waiting = true;
-                            }
-                            ("Agents", "AgentCallCompleted") => {
-                                println!();
-                                println!("{}Market created successfully!", CHECK);
-                                return Ok(());
-                            }
-                            ("Agents", "AgentCallFailed") => {
-                                println!();
-                                println!(
-                                    "{}Agent failed. Check chain events for details.",
-                                    style("Error: ").red()
-                                );
-                                return Ok(());
-                            }
-                            _ => {}
+                for ev in resume_events.iter().flatten() {
+                    match (ev.pallet_name(), ev.variant_name()) {
+                        ("Agents", "AgentRunWaitingForInput") => {
+                            still_waiting = true;
+                        }
+                        ("Agents", "AgentCallCompleted") => {
+                            println!();
+                            println!("{}Market created successfully!", CHECK);
+                            return Ok(());
+                        }
+                        ("Agents", "AgentCallFailed") => {
+                            println!();
+                            println!(
+                                "{}Agent failed. Check chain events for details.",
+                                style("Error: ").red()
+                            );
+                            return Ok(());
                         }
+                        _ => {}
                     }
                 }
 
@@ -479,12 +477,14 @@ async fn resolve_market(cli: &Cli, market_id: u64) -> Result<()> {
     let mut call_data = vec![0x03, 0x00, 0x00, 0x01];
     call_data.extend_from_slice(&market_id.encode());
 
+    contract::ensure_call_succeeds(&api, signer.public_key().0, contract_addr, 0, &call_data).await?;
+
     // pallet_contracts::call(dest, value, gas_limit, storage_deposit_limit, data)
     let tx = subxt::dynamic::tx(
         "Contracts",
         "call",
         vec![
-            Value::unnamed_variant("Id", [Value::from_bytes(&contract_addr)]),
+            Value::unnamed_variant("Id", [Value::from_bytes(contract_addr)]),
             Value::u128(0), // value
             Value::unnamed_variant("Limited", [Value::u128(10_000_000_000)]), // gas_limit (Weight as single u64 for ref_time)
             Value::unnamed_variant("None", []), // storage_deposit_limit
@@ -606,11 +606,13 @@ async fn place_bet(cli: &Cli, market_id: u64, option_index: u8, amount: u128) ->
     call_data.extend_from_slice(&option_index.encode());
     call_data.extend_from_slice(&amount.encode());
 
+    contract::ensure_call_succeeds(&api, signer.public_key().0, contract_addr, amount, &call_data).await?;
+
     let tx = subxt::dynamic::tx(
         "Contracts",
         "call",
         vec![
-            Value::unnamed_variant("Id", [Value::from_bytes(&contract_addr)]),
+            Value::unnamed_variant("Id", [Value::from_bytes(contract_addr)]),
             Value::u128(amount), // value - transfer amount for the bet
             Value::unnamed_variant("Limited", [Value::u128(10_000_000_000)]),
             Value::unnamed_variant("None", []),
@@ -667,11 +669,13 @@ async fn claim_winnings(cli: &Cli, market_id: u64) -> Result<()> {
     let mut call_data = vec![0x05, 0x00, 0x00, 0x01];
     call_data.extend_from_slice(&market_id.encode());
 
+    contract::ensure_call_succeeds(&api, signer.public_key().0, contract_addr, 0, &call_data).await?;
+
     let tx = subxt::dynamic::tx(
         "Contracts",
         "call",
         vec![
-            Value::unnamed_variant("Id", [Value::from_bytes(&contract_addr)]),
+            Value::unnamed_variant("Id", [Value::from_bytes(contract_addr)]),
             Value::u128(0),
             Value::unnamed_variant("Limited", [Value::u128(10_000_000_000)]),
             Value::unnamed_variant("None", []),
@@ -0,0 +1,128 @@
+//! Per-call cost as the number of markets and positions grows
+//!
+//! Each benchmark runs the same call against contracts holding 10, 1 000
+//! and 10 000 markets (with 10 positions each). Markets and positions are
+//! looked up by key, so timings and storage access counts should stay flat
+//! across sizes.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use parity_scale_codec::Encode;
+use prediction_market::chain_ext::MockChainExtension;
+use prediction_market::storage::{keys, AccessStats, Storage};
+use prediction_market::{AccountId, AgentCallbackPayload, MarketId, PredictionMarket, ResolutionResult};
+
+const ADMIN: AccountId = [1u8; 32];
+const CREATOR: AccountId = [10u8; 32];
+const RESOLVER: AccountId = [11u8; 32];
+const CALLBACK_ORIGIN: AccountId = [12u8; 32];
+const BETTOR: AccountId = [2u8; 32];
+
+const SIZES: [u64; 3] = [10, 1_000, 10_000];
+const BETTORS_PER_MARKET: u8 = 10;
+
+fn populated(markets: u64) -> PredictionMarket {
+    let mut contract = PredictionMarket::new(ADMIN);
+    contract.set_market_creator(ADMIN, CREATOR).unwrap();
+    contract.set_resolver_oracle(ADMIN, RESOLVER).unwrap();
+    contract.set_callback_origin(ADMIN, CALLBACK_ORIGIN).unwrap();
+    for _ in 0..markets {
+        let id = contract
+            .create_market(
+                CREATOR,
+                "Will BTC hit 100k?".into(),
+                vec!["Yes".into(), "No".into()],
+                "Price >= $100,000 on CoinGecko".into(),
+                "https://coingecko.com".into(),
+                100,
+                0,
+            )
+            .unwrap();
+        for b in 0..BETTORS_PER_MARKET {
+            contract.place_bet([100 + b; 32], id, b % 2, 1_000, 1_000, 0).unwrap();
+        }
+    }
+    contract
+}
+
+fn resolve(contract: &mut PredictionMarket, market_id: MarketId) {
+    contract
+        .request_resolution(market_id, 100, &mut MockChainExtension::queueing_from(1))
+        .unwrap();
+    let result = ResolutionResult {
+        market_id,
+        winning_option: 0,
+        void: false,
+        confidence_pct: 95,
+        evidence_summary: "BTC at $101k".into(),
+    };
+    contract
+        .on_resolution_complete(CALLBACK_ORIGIN, AgentCallbackPayload {
+            request_id: 1,
+            run_id: 1,
+            success: true,
+            output: result.encode(),
+        }, 100, &mut MockChainExtension::default())
+        .unwrap();
+}
+
+fn bench_calls(c: &mut Criterion) {
+    for size in SIZES {
+        let mut contract = populated(size);
+        let market_id = size - 1;
+
+        let mut group = c.benchmark_group("place_bet");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &market_id, |b, &id| {
+            b.iter(|| contract.place_bet(BETTOR, id, 0, 1, 1, 0).unwrap())
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("get_market");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &market_id, |b, &id| {
+            b.iter(|| contract.get_market(id).unwrap())
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("get_position");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &market_id, |b, &id| {
+            b.iter(|| contract.get_position(id, BETTOR))
+        });
+        group.finish();
+
+        contract.storage().reset_stats();
+        contract.get_position(market_id, BETTOR);
+        // One keyed read, however many markets there are
+        assert_eq!(contract.storage().stats(), AccessStats { reads: 1, writes: 0 });
+
+        contract.place_bet(BETTOR, market_id, 0, 1_000, 1_000, 0).unwrap();
+        resolve(&mut contract, market_id);
+
+        // Claiming clears the position and draws down escrow, so write those
+        // entries back before every claim. The refund is a few keyed writes and
+        // the reload reads only the config.
+        let snapshot: Vec<_> = [keys::position(market_id, &BETTOR), keys::market(market_id), keys::total_escrowed()]
+            .into_iter()
+            .map(|key| {
+                let value = contract.storage().get_raw(&key).unwrap();
+                (key, value)
+            })
+            .collect();
+        let mut storage = Some(contract.into_storage());
+
+        let mut group = c.benchmark_group("claim_winnings");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &market_id, |b, &id| {
+            b.iter(|| {
+                let mut refunded = storage.take().unwrap();
+                for (key, value) in &snapshot {
+                    refunded.set_raw(key, value);
+                }
+                let mut contract = PredictionMarket::load(refunded).unwrap();
+                contract.claim_winnings(BETTOR, id).unwrap();
+                storage = Some(contract.into_storage());
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_calls);
+criterion_main!(benches);
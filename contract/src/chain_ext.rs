@@ -0,0 +1,255 @@
+//! Chain extension bindings for agent requests
+//!
+//! The contract asks the runtime to run an agent by calling the chain
+//! extension function [`REQUEST_AGENT_RUN`] with a SCALE-encoded
+//! [`ContractAgentRequest`]. The extension returns a status code and, on
+//! success, a SCALE-encoded [`AgentRunReceipt`].
+//!
+//! The raw call sits behind the [`ChainExtension`] trait: the contract host
+//! forwards it to the runtime on-chain, and [`MockChainExtension`] simulates
+//! queueing and the extension's failure modes in tests.
+
+use alloc::vec::Vec;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+
+use crate::{AgentRunReceipt, BlockNumber, ContractAgentRequest, Error};
+
+/// Function ID of the agent-request call
+pub const REQUEST_AGENT_RUN: u32 = 0x0000_0001;
+
+/// Status codes returned by the agent-request call
+pub mod status {
+    /// The run was queued and the output holds its receipt
+    pub const SUCCESS: u32 = 0;
+    /// The target account is not a registered agent
+    pub const AGENT_NOT_FOUND: u32 = 1;
+    /// The agent does not accept requests from this contract
+    pub const AGENT_REJECTED: u32 = 2;
+    /// The contract cannot pay for the agent run
+    pub const INSUFFICIENT_FUNDS: u32 = 3;
+    /// The runtime's agent queue is full
+    pub const QUEUE_FULL: u32 = 4;
+    /// The request could not be decoded or has an invalid TTL or callback
+    pub const INVALID_REQUEST: u32 = 5;
+}
+
+/// Failure reported by the agent-request call
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum ChainExtError {
+    /// The target account is not a registered agent
+    AgentNotFound,
+    /// The agent does not accept requests from this contract
+    AgentRejected,
+    /// The contract cannot pay for the agent run
+    InsufficientFunds,
+    /// The runtime's agent queue is full
+    QueueFull,
+    /// The runtime refused the request as malformed
+    InvalidRequest,
+    /// The extension returned a status code this contract does not know
+    UnknownStatus(u32),
+    /// The extension reported success but the receipt could not be decoded
+    MalformedReceipt,
+}
+
+impl ChainExtError {
+    /// Map a non-success status code to its error
+    pub fn from_status(code: u32) -> Self {
+        match code {
+            status::AGENT_NOT_FOUND => Self::AgentNotFound,
+            status::AGENT_REJECTED => Self::AgentRejected,
+            status::INSUFFICIENT_FUNDS => Self::InsufficientFunds,
+            status::QUEUE_FULL => Self::QueueFull,
+            status::INVALID_REQUEST => Self::InvalidRequest,
+            other => Self::UnknownStatus(other),
+        }
+    }
+
+    /// Status code the extension returns for this error
+    ///
+    /// [`MalformedReceipt`](Self::MalformedReceipt) is detected by the contract
+    /// and has no status code of its own.
+    pub fn status(&self) -> Option<u32> {
+        match self {
+            Self::AgentNotFound => Some(status::AGENT_NOT_FOUND),
+            Self::AgentRejected => Some(status::AGENT_REJECTED),
+            Self::InsufficientFunds => Some(status::INSUFFICIENT_FUNDS),
+            Self::QueueFull => Some(status::QUEUE_FULL),
+            Self::InvalidRequest => Some(status::INVALID_REQUEST),
+            Self::UnknownStatus(code) => Some(*code),
+            Self::MalformedReceipt => None,
+        }
+    }
+}
+
+impl From<ChainExtError> for Error {
+    fn from(err: ChainExtError) -> Self {
+        match err {
+            ChainExtError::AgentNotFound => Error::AgentNotFound,
+            ChainExtError::AgentRejected => Error::AgentRequestRejected,
+            ChainExtError::InsufficientFunds => Error::InsufficientAgentFunds,
+            ChainExtError::QueueFull
+            | ChainExtError::InvalidRequest
+            | ChainExtError::UnknownStatus(_)
+            | ChainExtError::MalformedReceipt => Error::AgentRequestFailed,
+        }
+    }
+}
+
+/// Decode the agent-request call's status code and output into a receipt
+pub fn decode_receipt(code: u32, output: &[u8]) -> Result<AgentRunReceipt, ChainExtError> {
+    if code != status::SUCCESS {
+        return Err(ChainExtError::from_status(code));
+    }
+    AgentRunReceipt::decode(&mut &output[..]).map_err(|_| ChainExtError::MalformedReceipt)
+}
+
+/// Chain extension available to the contract
+pub trait ChainExtension {
+    /// Invoke extension function `func_id`, returning its status code and output
+    fn call(&mut self, func_id: u32, input: &[u8]) -> (u32, Vec<u8>);
+
+    /// Queue an agent run and return its receipt
+    fn request_agent_run(&mut self, request: &ContractAgentRequest) -> Result<AgentRunReceipt, ChainExtError> {
+        let (code, output) = self.call(REQUEST_AGENT_RUN, &request.encode());
+        decode_receipt(code, &output)
+    }
+}
+
+/// Behaviour of [`MockChainExtension`] for the next request
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub enum MockResponse {
+    /// Queue the run and return a receipt
+    #[default]
+    Queue,
+    /// Return this status code with no output
+    Status(u32),
+    /// Report success with these raw output bytes
+    Raw(Vec<u8>),
+}
+
+/// In-memory chain extension for tests and off-chain simulation
+///
+/// Queued runs get consecutive request IDs and start one block after the run
+/// queued before them.
+#[derive(Clone, Default, Debug)]
+pub struct MockChainExtension {
+    /// Requests queued so far, in submission order
+    pub queued: Vec<ContractAgentRequest>,
+    /// Request ID assigned to the next queued run
+    pub next_request_id: u64,
+    /// Block the first queued run is estimated to start at
+    pub start_block: BlockNumber,
+    /// How to answer the next request; reset to [`MockResponse::Queue`] after use
+    pub response: MockResponse,
+}
+
+impl MockChainExtension {
+    /// Extension that queues every request, starting at `request_id`
+    pub fn queueing_from(request_id: u64) -> Self {
+        Self {
+            next_request_id: request_id,
+            ..Self::default()
+        }
+    }
+
+    /// Answer the next request with a failure status
+    pub fn fail_next(&mut self, err: ChainExtError) {
+        self.response = match err.status() {
+            Some(code) => MockResponse::Status(code),
+            None => MockResponse::Raw(Vec::new()),
+        };
+    }
+}
+
+impl ChainExtension for MockChainExtension {
+    fn call(&mut self, func_id: u32, mut input: &[u8]) -> (u32, Vec<u8>) {
+        if func_id != REQUEST_AGENT_RUN {
+            return (status::INVALID_REQUEST, Vec::new());
+        }
+        let request = match ContractAgentRequest::decode(&mut input) {
+            Ok(request) => request,
+            Err(_) => return (status::INVALID_REQUEST, Vec::new()),
+        };
+        match core::mem::take(&mut self.response) {
+            MockResponse::Queue => {
+                let receipt = AgentRunReceipt {
+                    request_id: self.next_request_id,
+                    estimated_start_block: self.start_block + self.queued.len() as BlockNumber,
+                };
+                self.next_request_id += 1;
+                self.queued.push(request);
+                (status::SUCCESS, receipt.encode())
+            }
+            MockResponse::Status(code) => (code, Vec::new()),
+            MockResponse::Raw(output) => (status::SUCCESS, output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn request() -> ContractAgentRequest {
+        ContractAgentRequest {
+            target_agent: [11u8; 32],
+            input: vec![1, 2, 3],
+            ttl_blocks: 100,
+            callback: None,
+        }
+    }
+
+    #[test]
+    fn test_mock_queues_requests() {
+        let mut ext = MockChainExtension::queueing_from(7);
+        ext.start_block = 50;
+
+        let first = ext.request_agent_run(&request()).unwrap();
+        let second = ext.request_agent_run(&request()).unwrap();
+        assert_eq!(first, AgentRunReceipt { request_id: 7, estimated_start_block: 50 });
+        assert_eq!(second, AgentRunReceipt { request_id: 8, estimated_start_block: 51 });
+        assert_eq!(ext.queued.len(), 2);
+        assert_eq!(ext.queued[0].target_agent, [11u8; 32]);
+    }
+
+    #[test]
+    fn test_mock_failures_are_one_shot() {
+        let mut ext = MockChainExtension::queueing_from(1);
+
+        ext.fail_next(ChainExtError::AgentRejected);
+        assert_eq!(ext.request_agent_run(&request()), Err(ChainExtError::AgentRejected));
+        ext.fail_next(ChainExtError::InsufficientFunds);
+        assert_eq!(ext.request_agent_run(&request()), Err(ChainExtError::InsufficientFunds));
+        assert!(ext.queued.is_empty());
+
+        assert_eq!(ext.request_agent_run(&request()).unwrap().request_id, 1);
+    }
+
+    #[test]
+    fn test_decode_receipt() {
+        let receipt = AgentRunReceipt { request_id: 42, estimated_start_block: 9 };
+        assert_eq!(decode_receipt(status::SUCCESS, &receipt.encode()), Ok(receipt));
+        assert_eq!(decode_receipt(status::SUCCESS, &[1, 2]), Err(ChainExtError::MalformedReceipt));
+        assert_eq!(decode_receipt(status::QUEUE_FULL, &[]), Err(ChainExtError::QueueFull));
+        assert_eq!(decode_receipt(99, &[]), Err(ChainExtError::UnknownStatus(99)));
+    }
+
+    #[test]
+    fn test_status_codes_roundtrip() {
+        for code in 1..=6 {
+            let err = ChainExtError::from_status(code);
+            assert_eq!(err.status(), Some(code));
+        }
+    }
+
+    #[test]
+    fn test_errors_map_to_contract_errors() {
+        assert_eq!(Error::from(ChainExtError::AgentNotFound), Error::AgentNotFound);
+        assert_eq!(Error::from(ChainExtError::AgentRejected), Error::AgentRequestRejected);
+        assert_eq!(Error::from(ChainExtError::InsufficientFunds), Error::InsufficientAgentFunds);
+        assert_eq!(Error::from(ChainExtError::MalformedReceipt), Error::AgentRequestFailed);
+    }
+}
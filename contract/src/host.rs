@@ -0,0 +1,190 @@
+//! WASM host bindings
+//!
+//! Thin wrappers around the contracts pallet host functions plus the
+//! `deploy`/`call` exports. All contract logic lives in [`PredictionMarket`];
+//! this module only moves bytes between the host and the dispatcher.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use parity_scale_codec::{Decode, Encode};
+
+use crate::chain_ext::ChainExtension;
+use crate::storage::Storage;
+use crate::{AccountId, Balance, BlockNumber, CallContext, DispatchOutput, Error, Event, PredictionMarket, Transfer};
+
+/// Scratch buffer size for host reads, and so the largest storage value
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Return flag telling the host to roll back state changes
+const FLAG_REVERT: u32 = 1;
+
+/// Host return code for a successful call
+const SUCCESS: u32 = 0;
+
+/// Host return code for a missing storage key
+const KEY_NOT_FOUND: u32 = 3;
+
+/// Output buffer size for chain extension calls
+const CHAIN_EXT_OUTPUT_SIZE: usize = 256;
+
+mod sys {
+    #[link(wasm_import_module = "seal0")]
+    extern "C" {
+        pub fn seal_input(buf_ptr: *mut u8, buf_len_ptr: *mut u32);
+        pub fn seal_return(flags: u32, data_ptr: *const u8, data_len: u32) -> !;
+        pub fn seal_caller(out_ptr: *mut u8, out_len_ptr: *mut u32);
+        pub fn seal_block_number(out_ptr: *mut u8, out_len_ptr: *mut u32);
+        pub fn seal_value_transferred(out_ptr: *mut u8, out_len_ptr: *mut u32);
+        pub fn seal_deposit_event(topics_ptr: *const u8, topics_len: u32, data_ptr: *const u8, data_len: u32);
+        pub fn seal_transfer(account_ptr: *const u8, account_len: u32, value_ptr: *const u8, value_len: u32) -> u32;
+        pub fn call_chain_extension(
+            id: u32,
+            input_ptr: *const u8,
+            input_len: u32,
+            output_ptr: *mut u8,
+            output_len_ptr: *mut u32,
+        ) -> u32;
+    }
+
+    #[link(wasm_import_module = "seal1")]
+    extern "C" {
+        pub fn get_storage(key_ptr: *const u8, key_len: u32, out_ptr: *mut u8, out_len_ptr: *mut u32) -> u32;
+        pub fn clear_storage(key_ptr: *const u8, key_len: u32) -> u32;
+    }
+
+    #[link(wasm_import_module = "seal2")]
+    extern "C" {
+        pub fn set_storage(key_ptr: *const u8, key_len: u32, value_ptr: *const u8, value_len: u32) -> u32;
+    }
+}
+
+/// Read a host value into a fresh buffer of `capacity` bytes
+fn read_with(capacity: usize, f: unsafe extern "C" fn(*mut u8, *mut u32)) -> Vec<u8> {
+    let mut buf = vec![0u8; capacity];
+    let mut len = buf.len() as u32;
+    unsafe { f(buf.as_mut_ptr(), &mut len) };
+    buf.truncate(len as usize);
+    buf
+}
+
+/// Read and decode a fixed-size host value
+fn read_decoded<T: Decode>(f: unsafe extern "C" fn(*mut u8, *mut u32)) -> T {
+    let buf = read_with(64, f);
+    T::decode(&mut &buf[..]).unwrap_or_else(|_| panic!("host returned malformed value"))
+}
+
+fn input() -> Vec<u8> {
+    read_with(BUFFER_SIZE, sys::seal_input)
+}
+
+fn context() -> CallContext {
+    CallContext {
+        caller: read_decoded::<AccountId>(sys::seal_caller),
+        block_number: read_decoded::<BlockNumber>(sys::seal_block_number),
+        value_transferred: read_decoded::<Balance>(sys::seal_value_transferred),
+    }
+}
+
+/// Contract storage provided by the host
+pub struct HostStorage;
+
+impl Storage for HostStorage {
+    fn get_raw(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = vec![0u8; BUFFER_SIZE];
+        let mut len = buf.len() as u32;
+        let code = unsafe { sys::get_storage(key.as_ptr(), key.len() as u32, buf.as_mut_ptr(), &mut len) };
+        match code {
+            SUCCESS => {}
+            KEY_NOT_FOUND => return None,
+            _ => panic!("storage read failed with code {code}"),
+        }
+        if len as usize > buf.len() {
+            panic!("storage value larger than the read buffer");
+        }
+        buf.truncate(len as usize);
+        Some(buf)
+    }
+
+    fn set_raw(&mut self, key: &[u8], value: &[u8]) {
+        // Anything larger could not be read back in full
+        if value.len() > BUFFER_SIZE {
+            panic!("storage value larger than the read buffer");
+        }
+        unsafe { sys::set_storage(key.as_ptr(), key.len() as u32, value.as_ptr(), value.len() as u32) };
+    }
+
+    fn clear(&mut self, key: &[u8]) {
+        unsafe { sys::clear_storage(key.as_ptr(), key.len() as u32) };
+    }
+}
+
+/// Chain extension provided by the runtime
+pub struct HostChainExtension;
+
+impl ChainExtension for HostChainExtension {
+    fn call(&mut self, func_id: u32, input: &[u8]) -> (u32, Vec<u8>) {
+        let mut buf = vec![0u8; CHAIN_EXT_OUTPUT_SIZE];
+        let mut len = buf.len() as u32;
+        let code = unsafe {
+            sys::call_chain_extension(func_id, input.as_ptr(), input.len() as u32, buf.as_mut_ptr(), &mut len)
+        };
+        buf.truncate(len as usize);
+        (code, buf)
+    }
+}
+
+/// Send `transfer.amount` from the contract's balance to `transfer.to`
+fn transfer(transfer: &Transfer) -> Result<(), Error> {
+    let value = transfer.amount.encode();
+    let code = unsafe {
+        sys::seal_transfer(transfer.to.as_ptr(), transfer.to.len() as u32, value.as_ptr(), value.len() as u32)
+    };
+    if code != 0 {
+        return Err(Error::TransferFailed);
+    }
+    Ok(())
+}
+
+fn deposit_event(event: &Event) {
+    let topics = event.topics().encode();
+    let data = event.encode();
+    unsafe {
+        sys::seal_deposit_event(topics.as_ptr(), topics.len() as u32, data.as_ptr(), data.len() as u32);
+    }
+}
+
+fn return_output(output: DispatchOutput) -> ! {
+    let flags = if output.revert { FLAG_REVERT } else { 0 };
+    unsafe { sys::seal_return(flags, output.data.as_ptr(), output.data.len() as u32) }
+}
+
+#[no_mangle]
+pub extern "C" fn deploy() {
+    let result = PredictionMarket::instantiate(HostStorage, &input()).map(|mut contract| {
+        for event in contract.take_events() {
+            deposit_event(&event);
+        }
+    });
+    return_output(DispatchOutput {
+        revert: result.is_err(),
+        data: result.encode(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let ctx = context();
+    let mut contract = PredictionMarket::load(HostStorage)
+        .unwrap_or_else(|| panic!("contract state not initialized"));
+    let output = contract.dispatch(&ctx, &input(), &mut HostChainExtension);
+    if let Err(err) = contract.take_transfers().iter().try_for_each(transfer) {
+        return_output(DispatchOutput {
+            revert: true,
+            data: Result::<(), Error>::Err(err).encode(),
+        })
+    }
+    for event in contract.take_events() {
+        deposit_event(&event);
+    }
+    return_output(output)
+}
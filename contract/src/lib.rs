@@ -5,28 +5,59 @@
 //! - Resolver Oracle Agent: Resolves markets using external data sources
 //!
 //! # Pricing Model
-//! Uses parimutuel betting - no odds at bet time, payout is proportional to pool:
-//!   Payout = (user_shares / winning_option_shares) * total_pool
+//! Markets use parimutuel betting by default - no odds at bet time, payout is
+//! proportional to pool:
+//!   Payout = (user_shares / winning_option_shares) * (total_pool - fees)
+//!
+//! Fees are a protocol fee (accrued for the treasury) and an optional creator
+//! fee, both in basis points of the losing side's stakes.
+//!
+//! Markets created with `create_lmsr_market` are instead priced by an LMSR
+//! market maker (see [`lmsr`]): `buy_shares` and `sell_shares` trade at a
+//! quoted price, each winning share pays 1, and the creator funds the market
+//! maker's worst-case loss. These markets charge no fees.
 //!
 //! # Flow
-//! 1. Admin deploys contract, sets agent addresses
+//! 1. Admin deploys contract, sets agent addresses, and can delegate parts of
+//!    its powers with `set_role` (see [`Role`]) or hand over with `propose_admin`
 //! 2. Market Creator agent calls `create_market` with options
-//! 3. Users place bets via `place_bet(market_id, option_index, amount)`
+//! 3. Users place bets via `place_bet(market_id, option_index, amount)`, sending `amount` as
+//!    value, until betting closes at the deadline (or a configured cutoff before it).
+//!    Until then they can also take stakes back with `withdraw_bet`, less any exit penalty
 //! 4. After deadline, anyone calls `request_resolution`
-//! 5. Contract requests Resolver Oracle via chain extension
-//! 6. Resolver completes, callback triggers `on_resolution_complete`
-//! 7. Winners claim via `claim_winnings`
+//! 5. Contract requests every Resolver Oracle via chain extension
+//! 6. Resolvers complete, callbacks trigger `on_resolution_complete`, and the
+//!    market settles once a quorum of them report the same outcome
+//!    (failed or timed-out rounds can be re-requested a bounded number of
+//!    times; after that, or if the resolvers disagree, the arbitrator
+//!    resolves or voids the market)
+//! 7. If a dispute window is configured, the outcome is only proposed: a bettor
+//!    can dispute it with a bond, triggering a second run or arbitration,
+//!    and otherwise anyone finalizes it once the window closes
+//! 8. Winners claim via `claim_winnings` and are paid from the escrowed stakes
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 extern crate alloc;
 
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use parity_scale_codec::{Decode, Encode};
 use scale_info::TypeInfo;
 
+pub mod chain_ext;
+pub mod lmsr;
+pub mod math;
+pub mod storage;
+
+#[cfg(target_arch = "wasm32")]
+mod host;
+
+use chain_ext::ChainExtension;
+use storage::{keys, MemoryStorage, Storage};
+
 // ============================================================================
 // Types
 // ============================================================================
@@ -49,21 +80,78 @@ pub type OptionIndex = u8;
 /// Maximum number of options per market
 pub const MAX_OPTIONS: usize = 10;
 
+/// Blocks a resolver run has to deliver its callback (~10 minutes at 6s blocks)
+pub const RESOLUTION_TTL_BLOCKS: u32 = 100;
+
+/// Maximum number of resolver agents
+pub const MAX_RESOLVERS: usize = 5;
+
+/// Default number of resolver runs per market before the admin fallback
+pub const DEFAULT_MAX_RESOLUTION_ATTEMPTS: u8 = 3;
+
+/// Basis points in one whole (100%)
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Highest protocol fee the admin can set (10%)
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 1_000;
+
+/// Highest creator fee a market can charge (5%)
+pub const MAX_CREATOR_FEE_BPS: u16 = 500;
+
+/// Highest penalty the admin can set for withdrawing a bet early (10%)
+pub const MAX_EXIT_PENALTY_BPS: u16 = 1_000;
+
+/// Longest resolver evidence summary kept with a market, in bytes
+pub const MAX_EVIDENCE_SUMMARY_LEN: usize = 1_024;
+
+/// Cut `summary` to at most [`MAX_EVIDENCE_SUMMARY_LEN`] bytes on a character boundary
+///
+/// Keeps a resolver's answer from growing the market record past what
+/// storage can hold.
+fn truncate_summary(mut summary: String) -> String {
+    if summary.len() > MAX_EVIDENCE_SUMMARY_LEN {
+        let end = (0..=MAX_EVIDENCE_SUMMARY_LEN).rev().find(|&i| summary.is_char_boundary(i)).unwrap_or(0);
+        summary.truncate(end);
+    }
+    summary
+}
+
+/// `bps` basis points of `amount`, rounded down
+fn bps_of(amount: Balance, bps: u16) -> Result<Balance, Error> {
+    math::mul_div(amount, bps as Balance, BPS_DENOMINATOR as Balance).ok_or(Error::Overflow)
+}
+
 /// Status of a prediction market
-#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
 pub enum MarketStatus {
     /// Market is open for betting
+    #[default]
     Open,
     /// Resolution has been requested, waiting for oracle
     PendingResolution,
     /// Market has been resolved
     Resolved,
+    /// Market was voided; bettors reclaim their stakes via `claim_refund`
+    Voided,
+    /// The oracle proposed an outcome that can be disputed until the window closes
+    Proposed,
+    /// A bettor disputed the proposed outcome; awaiting a second run or arbitration
+    Disputed,
+    /// Market was cancelled before resolution; bettors reclaim their stakes via `claim_refund`
+    Cancelled,
 }
 
-impl Default for MarketStatus {
-    fn default() -> Self {
-        MarketStatus::Open
-    }
+/// How a market prices its shares
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Pricing {
+    /// Stakes are pooled and winners split the pool; no price at bet time
+    #[default]
+    Parimutuel,
+    /// An LMSR market maker quotes every trade; each winning share pays 1
+    Lmsr {
+        /// Liquidity parameter `b`: higher means prices move less per share
+        liquidity: Balance,
+    },
 }
 
 /// A prediction market with multiple options
@@ -83,24 +171,273 @@ pub struct Market {
     pub creator: AccountId,
     /// Block number after which resolution can be requested
     pub resolution_deadline: BlockNumber,
+    /// First block at which bets are no longer accepted
+    pub betting_closes_at: BlockNumber,
     /// Total shares per option (indexed by option_index)
     pub shares_per_option: Vec<Balance>,
     /// Current status
     pub status: MarketStatus,
     /// Winning option index (None = unresolved)
     pub winning_option: Option<OptionIndex>,
+    /// Funds held for this market: stakes received minus payouts made
+    pub escrowed: Balance,
+    /// Resolver runs requested for this market so far
+    pub resolution_attempts: u8,
+    /// How the pool is settled, fixed at resolution if nobody bet on the winning option
+    pub no_winner_policy: Option<NoWinnerPolicy>,
+    /// Protocol fee on the losing side, fixed when the market is created
+    pub protocol_fee_bps: u16,
+    /// Creator fee on the losing side, set by the creator
+    pub creator_fee_bps: u16,
+    /// Outcome proposed by the oracle, kept once the market is settled
+    pub proposal: Option<Proposal>,
+    /// Minimum resolver confidence for this market, overriding the global one
+    pub min_confidence_pct: Option<u8>,
+    /// Oracle report that decided the market, kept for audit and disputes
+    pub resolution: Option<Resolution>,
+    /// Pricing mode, fixed when the market is created
+    pub pricing: Pricing,
+    /// Penalty on bets withdrawn before resolution, fixed when the market is created
+    pub exit_penalty_bps: u16,
+    /// Bet limits for this market, overriding the global ones
+    pub bet_limits: Option<BetLimits>,
+    /// Resolution was escalated, so only the admin fallback can settle the market
+    pub escalated: bool,
+    /// Oracle report a disputed proposal was based on, kept when the second round replaces it
+    pub disputed_resolution: Option<Resolution>,
 }
 
 impl Market {
     /// Total pool across all options
-    pub fn total_pool(&self) -> Balance {
-        self.shares_per_option.iter().sum()
+    pub fn total_pool(&self) -> Result<Balance, Error> {
+        math::checked_sum(&self.shares_per_option)
     }
 
     /// Check if this is a binary (Yes/No) market
     pub fn is_binary(&self) -> bool {
         self.options.len() == 2
     }
+
+    /// Protocol and creator fees taken from the losing side of this resolved market
+    ///
+    /// Nothing is taken before resolution or when nobody bet on the winning
+    /// option, so refunded stakes are returned in full.
+    pub fn fees(&self) -> Result<(Balance, Balance), Error> {
+        let winning_pool = match self.winning_option {
+            Some(idx) if self.status == MarketStatus::Resolved => self.shares_per_option[idx as usize],
+            _ => 0,
+        };
+        if winning_pool == 0 {
+            return Ok((0, 0));
+        }
+        let losing_pool = self.total_pool()?.checked_sub(winning_pool).ok_or(Error::Overflow)?;
+        Ok((bps_of(losing_pool, self.protocol_fee_bps)?, bps_of(losing_pool, self.creator_fee_bps)?))
+    }
+
+    /// Winnings owed to `position` in this resolved market
+    ///
+    /// Payout = (user_shares / winning_pool) * (total_pool - fees)
+    pub fn winnings(&self, position: &Position) -> Result<Balance, Error> {
+        if self.status != MarketStatus::Resolved {
+            return Err(Error::MarketNotResolved);
+        }
+        let winning_idx = self.winning_option.ok_or(Error::MarketNotResolved)? as usize;
+
+        let winning_pool = self.shares_per_option[winning_idx];
+        if winning_pool == 0 {
+            return Err(Error::WinningPoolEmpty);
+        }
+
+        let winning_shares = position.shares.get(winning_idx).copied().unwrap_or_default();
+        if winning_shares == 0 {
+            return Err(Error::NoWinningShares);
+        }
+        if let Pricing::Lmsr { .. } = self.pricing {
+            return Ok(winning_shares);
+        }
+
+        let (protocol_fee, creator_fee) = self.fees()?;
+        let distributable = self
+            .total_pool()?
+            .checked_sub(protocol_fee)
+            .and_then(|rest| rest.checked_sub(creator_fee))
+            .ok_or(Error::Overflow)?;
+        math::mul_div(winning_shares, distributable, winning_pool).ok_or(Error::Overflow)
+    }
+
+    /// Stake returned to `position` when the market refunds
+    ///
+    /// In an LMSR market each share is redeemed at its final price.
+    pub fn refund(&self, position: &Position) -> Result<Balance, Error> {
+        let Pricing::Lmsr { liquidity } = self.pricing else {
+            return position.total_shares();
+        };
+        let prices = lmsr::prices(&self.shares_per_option, liquidity);
+        let values = position
+            .shares
+            .iter()
+            .zip(&prices)
+            .map(|(&shares, &price)| math::mul_div(shares, price, lmsr::ONE).ok_or(Error::Overflow))
+            .collect::<Result<Vec<_>, _>>()?;
+        math::checked_sum(&values)
+    }
+
+    /// Check if stakes are returned to bettors: the market was voided or
+    /// cancelled, or nobody bet on the winning option and the pool is refunded
+    pub fn is_refunding(&self) -> bool {
+        matches!(self.status, MarketStatus::Voided | MarketStatus::Cancelled)
+            || (self.status == MarketStatus::Resolved && self.no_winner_policy == Some(NoWinnerPolicy::Refund))
+    }
+}
+
+/// How a market is settled
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Outcome {
+    /// The market resolves in favour of this option
+    Winner(OptionIndex),
+    /// The market is voided and stakes are refunded
+    Void,
+}
+
+/// Outcome proposed by the oracle, open to dispute until `dispute_ends_at`
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Proposal {
+    /// Outcome the oracle reported
+    pub outcome: Outcome,
+    /// First block at which the proposal can no longer be disputed
+    pub dispute_ends_at: BlockNumber,
+    /// Dispute raised against the proposal, if any
+    pub dispute: Option<Dispute>,
+}
+
+/// Oracle report an outcome was proposed or settled on
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Resolution {
+    /// Outcome the resolver reported
+    pub outcome: Outcome,
+    /// Resolver's confidence in the outcome (0-100)
+    pub confidence_pct: u8,
+    /// Resolver's summary of the evidence, cut to [`MAX_EVIDENCE_SUMMARY_LEN`] bytes
+    pub evidence_summary: String,
+    /// Resolver agent that delivered the deciding report
+    pub resolver: AccountId,
+    /// Agent request the report answered
+    pub request_id: u64,
+    /// Agent run that produced the report
+    pub run_id: u64,
+    /// Block the report was received at
+    pub block: BlockNumber,
+}
+
+/// Challenge of a proposed outcome, backed by a bond
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Dispute {
+    /// Bettor who disputed the proposal
+    pub disputer: AccountId,
+    /// Bond held until the dispute is settled: returned if upheld, slashed otherwise
+    pub bond: Balance,
+}
+
+/// What happens when a resolution round fails to reach quorum because of
+/// answers below the confidence threshold
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum LowConfidenceAction {
+    /// Request another round, as for failed runs
+    #[default]
+    ReRequest,
+    /// Leave the market to the admin fallback; it cannot be re-requested
+    Escalate,
+    /// Void the market so stakes are refunded
+    Void,
+}
+
+/// Limits on bets placed with `place_bet`; `None` means unlimited
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct BetLimits {
+    /// Smallest accepted bet
+    pub min_bet: Option<Balance>,
+    /// Largest accepted bet
+    pub max_bet: Option<Balance>,
+    /// Largest total stake one account can hold across a market's options
+    pub max_position_per_account: Option<Balance>,
+}
+
+impl BetLimits {
+    /// Check that the limits are non-zero and leave room for at least one bet
+    fn validate(&self) -> Result<(), Error> {
+        let limits = [self.min_bet, self.max_bet, self.max_position_per_account];
+        if limits.contains(&Some(0)) {
+            return Err(Error::InvalidConfig);
+        }
+        let min_bet = self.min_bet.unwrap_or_default();
+        if self.max_bet.is_some_and(|max| max < min_bet)
+            || self.max_position_per_account.is_some_and(|max| max < min_bet)
+        {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(())
+    }
+
+    /// Check a bet of `amount` by an account already holding `staked` in the market
+    fn check(&self, amount: Balance, staked: Balance) -> Result<(), Error> {
+        if self.min_bet.is_some_and(|min| amount < min) {
+            return Err(Error::BetTooSmall);
+        }
+        if self.max_bet.is_some_and(|max| amount > max) {
+            return Err(Error::BetTooLarge);
+        }
+        self.check_position(staked.checked_add(amount).ok_or(Error::Overflow)?)
+    }
+
+    /// Check what a partial withdrawal leaves of a stake on an option: nothing, or at least a minimum bet
+    fn check_remaining(&self, remaining: Balance) -> Result<(), Error> {
+        if remaining > 0 && self.min_bet.is_some_and(|min| remaining < min) {
+            return Err(Error::BetTooSmall);
+        }
+        Ok(())
+    }
+
+    /// Check an account's total stake in the market against the cap
+    fn check_position(&self, total: Balance) -> Result<(), Error> {
+        if self.max_position_per_account.is_some_and(|max| total > max) {
+            return Err(Error::PositionLimitExceeded);
+        }
+        Ok(())
+    }
+}
+
+/// What happens to a resolved market's pool when nobody bet on the winning option
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum NoWinnerPolicy {
+    /// Every bettor reclaims their stake with `claim_refund`
+    #[default]
+    Refund,
+    /// The pool is swept to the treasury with `sweep_to_treasury`
+    Treasury,
+}
+
+/// Price of a trade in an LMSR market (see `quote`)
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Quote {
+    /// Exact cost of buying the shares now
+    pub buy_cost: Balance,
+    /// Exact proceeds of selling the shares now, if that many are outstanding
+    pub sell_proceeds: Option<Balance>,
+}
+
+/// What an account can claim from a market (see `get_claimable`)
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Claimable {
+    /// The market has not been settled yet
+    NotSettled,
+    /// Winnings to collect with `claim_winnings`
+    Winnings(Balance),
+    /// Stake to reclaim with `claim_refund`
+    Refund(Balance),
+    /// Nobody bet on the winning option and the pool goes to the treasury
+    PoolToTreasury,
+    /// No position, no winning shares, or already claimed
+    Nothing,
 }
 
 /// A user's position in a market (shares per option)
@@ -119,8 +456,8 @@ impl Position {
     }
 
     /// Total shares across all options
-    pub fn total_shares(&self) -> Balance {
-        self.shares.iter().sum()
+    pub fn total_shares(&self) -> Result<Balance, Error> {
+        math::checked_sum(&self.shares)
     }
 
     /// Check if position is empty
@@ -129,15 +466,433 @@ impl Position {
     }
 }
 
+/// Part of the admin's powers that can be delegated to another account
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Role {
+    /// Sets the market creator agent, resolution rules and bet limits
+    ConfigManager,
+    /// Sets fees and exit penalties and withdraws accrued fees
+    FeeManager,
+    /// Pauses new markets and new stakes in an emergency
+    Pauser,
+    /// Settles markets by hand: arbitration, fallback resolution, voiding and cancelling
+    Arbitrator,
+}
+
+/// Who holds the admin powers (see `get_roles`)
+///
+/// The admin holds every role itself; each role can also be given to one
+/// other account.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Roles {
+    /// Admin, holding every role
+    pub admin: AccountId,
+    /// Account proposed as the next admin, until it accepts
+    pub pending_admin: Option<AccountId>,
+    /// Account given [`Role::ConfigManager`]
+    pub config_manager: Option<AccountId>,
+    /// Account given [`Role::FeeManager`]
+    pub fee_manager: Option<AccountId>,
+    /// Account given [`Role::Pauser`]
+    pub pauser: Option<AccountId>,
+    /// Account given [`Role::Arbitrator`]
+    pub arbitrator: Option<AccountId>,
+}
+
 /// Contract configuration
-#[derive(Clone, Default, Encode, Decode, TypeInfo, Debug)]
+#[derive(Clone, Default, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
 pub struct Config {
     /// Admin who can update configuration
     pub admin: AccountId,
     /// Market creator agent (only this account can create markets)
     pub market_creator_agent: Option<AccountId>,
-    /// Resolver oracle agent (called to resolve markets)
-    pub resolver_oracle_agent: Option<AccountId>,
+    /// Resolver oracle agents, each asked to resolve every market
+    pub resolver_agents: Vec<AccountId>,
+    /// Resolvers that must report the same outcome to settle a market
+    pub resolver_quorum: u8,
+    /// Account the runtime uses when delivering agent callbacks
+    pub agent_callback_origin: Option<AccountId>,
+    /// Resolution rounds per market (first request plus automatic re-requests)
+    pub max_resolution_attempts: u8,
+    /// Account that receives funds owed to the protocol
+    pub treasury: Option<AccountId>,
+    /// How pools with no winning bets are settled
+    pub no_winner_policy: NoWinnerPolicy,
+    /// Blocks before a new market's deadline at which betting closes
+    pub betting_cutoff_blocks: BlockNumber,
+    /// Protocol fee charged on the losing side of new markets, in basis points
+    pub protocol_fee_bps: u16,
+    /// Blocks an oracle outcome can be disputed before it is final (0 = final at once)
+    pub dispute_window_blocks: BlockNumber,
+    /// Bond a bettor must send to dispute an outcome
+    pub dispute_bond: Balance,
+    /// Minimum resolver confidence for an answer to count (0-100)
+    pub min_confidence_pct: u8,
+    /// What to do when low-confidence answers keep a round from reaching quorum
+    pub low_confidence_action: LowConfidenceAction,
+    /// Penalty on bets withdrawn from new markets before resolution, in basis points
+    pub exit_penalty_bps: u16,
+    /// Limits on bets in markets without their own
+    pub bet_limits: BetLimits,
+    /// Account proposed as the next admin, until it calls `accept_admin`
+    pub pending_admin: Option<AccountId>,
+    /// Account holding [`Role::ConfigManager`] besides the admin
+    pub config_manager: Option<AccountId>,
+    /// Account holding [`Role::FeeManager`] besides the admin
+    pub fee_manager: Option<AccountId>,
+    /// Account holding [`Role::Pauser`] besides the admin
+    pub pauser: Option<AccountId>,
+    /// Account holding [`Role::Arbitrator`] besides the admin
+    pub arbitrator: Option<AccountId>,
+    /// Whether new markets and new stakes are paused
+    pub paused: bool,
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Errors returned by contract methods
+///
+/// Encoded into the call output so callers can decode the exact failure.
+/// New variants must be appended to keep existing encodings stable.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Error {
+    /// Call data is shorter than a selector or names an unknown function
+    UnknownSelector,
+    /// Call arguments could not be SCALE-decoded
+    InvalidArguments,
+    /// Caller is not allowed to perform this action
+    NotAuthorized,
+    /// No market creator agent has been configured
+    MarketCreatorNotConfigured,
+    /// No resolver oracle agent has been configured
+    ResolverNotConfigured,
+    /// Market must have at least 2 options
+    TooFewOptions,
+    /// Market has more than `MAX_OPTIONS` options
+    TooManyOptions,
+    /// No market exists with the given ID
+    MarketNotFound,
+    /// Market is not accepting bets or resolution requests
+    MarketNotOpen,
+    /// Option index is out of range for the market
+    InvalidOptionIndex,
+    /// Resolution requested before the market deadline
+    DeadlineNotReached,
+    /// Reserved: no longer returned, kept so later variants keep their index
+    OracleFailed,
+    /// Reserved: no longer returned, kept so later variants keep their index
+    InvalidResolutionResult,
+    /// Market is not waiting for a resolution
+    MarketNotPendingResolution,
+    /// Market has not been resolved yet
+    MarketNotResolved,
+    /// Caller has no position in the market
+    NoPosition,
+    /// Caller holds no shares in the winning option
+    NoWinningShares,
+    /// Nobody bet on the winning option
+    WinningPoolEmpty,
+    /// Callback request ID does not match the one recorded for the market
+    RequestIdMismatch,
+    /// Callback for this request ID was already processed
+    RequestAlreadyProcessed,
+    /// The chain extension did not accept the agent request
+    AgentRequestFailed,
+    /// The resolver oracle is not a registered agent
+    AgentNotFound,
+    /// The resolver oracle does not accept requests from this contract
+    AgentRequestRejected,
+    /// The contract cannot pay for the resolver oracle run
+    InsufficientAgentFunds,
+    /// Value transferred does not match the stake, dispute bond or LMSR subsidy
+    StakeMismatch,
+    /// Amount must be greater than zero, and an LMSR trade large enough to price
+    ZeroAmount,
+    /// Value was transferred to a method that does not accept it
+    NotPayable,
+    /// The host could not transfer a payout
+    TransferFailed,
+    /// A resolver run for the market is still within its TTL
+    ResolutionInProgress,
+    /// Market is not refunding stakes (not voided or cancelled, and not a refunded no-winner pool)
+    MarketNotVoided,
+    /// Setting is out of range
+    InvalidConfig,
+    /// No treasury account has been configured
+    TreasuryNotConfigured,
+    /// Market pool does not go to the treasury, or was already swept
+    NotTreasuryPool,
+    /// Betting on the market has closed
+    BettingClosed,
+    /// Fee exceeds `MAX_PROTOCOL_FEE_BPS` or `MAX_CREATOR_FEE_BPS`
+    FeeTooHigh,
+    /// There are no accrued fees to withdraw
+    NoFeesAccrued,
+    /// Market has no proposed outcome awaiting its dispute window
+    MarketNotProposed,
+    /// The dispute window of the proposed outcome has closed
+    DisputeWindowClosed,
+    /// The proposed outcome can still be disputed
+    DisputeWindowOpen,
+    /// The proposed outcome has already been disputed
+    AlreadyDisputed,
+    /// Market is not awaiting settlement of a dispute
+    MarketNotDisputed,
+    /// Market already has bets, so only the admin can cancel it
+    PoolNotEmpty,
+    /// A balance calculation overflowed
+    Overflow,
+    /// The call does not apply to the market's pricing mode
+    PricingMismatch,
+    /// Position holds fewer shares than the trade needs
+    InsufficientShares,
+    /// Trade cost exceeds the value sent, or proceeds fall below the minimum
+    SlippageExceeded,
+    /// Transfer exceeds the shares the caller was approved to move
+    InsufficientAllowance,
+    /// Shares cannot be transferred to, or approved for, the account they come from
+    InvalidRecipient,
+    /// Bet is below the market's minimum
+    BetTooSmall,
+    /// Bet is above the market's maximum
+    BetTooLarge,
+    /// Bet would take the account's stake in the market over its cap
+    PositionLimitExceeded,
+    /// New markets and new stakes are paused
+    ContractPaused,
+    /// Resolution was escalated and waits for the admin fallback
+    ResolutionEscalated,
+    /// The market has used all its resolution rounds and waits for the admin fallback
+    ResolutionAttemptsExhausted,
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+/// Events emitted on every state transition
+///
+/// Deposited with topics for the market and, where relevant, the account
+/// involved so indexers can filter by either (see [`Event::topics`]).
+/// New variants must be appended to keep existing encodings stable.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum Event {
+    /// A new market was created
+    MarketCreated {
+        market_id: MarketId,
+        creator: AccountId,
+        question: String,
+        options: Vec<String>,
+        resolution_deadline: BlockNumber,
+    },
+    /// A bet was placed on an option
+    BetPlaced {
+        market_id: MarketId,
+        account: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    },
+    /// Resolution was requested from the resolver oracle
+    ResolutionRequested {
+        market_id: MarketId,
+        resolver: AccountId,
+        request_id: u64,
+    },
+    /// The oracle resolved the market
+    MarketResolved {
+        market_id: MarketId,
+        winning_option: OptionIndex,
+    },
+    /// A winner claimed their payout
+    WinningsClaimed {
+        market_id: MarketId,
+        account: AccountId,
+        payout: Balance,
+    },
+    /// Contract configuration was updated
+    ConfigChanged {
+        changed_by: AccountId,
+        config: Box<Config>,
+    },
+    /// A resolver run failed or returned an unusable result
+    ResolutionFailed {
+        market_id: MarketId,
+        request_id: u64,
+        attempt: u8,
+    },
+    /// The market was voided and stakes became refundable
+    MarketVoided {
+        market_id: MarketId,
+    },
+    /// A bettor reclaimed their stake from a voided or unwon market
+    RefundClaimed {
+        market_id: MarketId,
+        account: AccountId,
+        amount: Balance,
+    },
+    /// The pool of a market nobody won was sent to the treasury
+    PoolSweptToTreasury {
+        market_id: MarketId,
+        treasury: AccountId,
+        amount: Balance,
+    },
+    /// Fees were taken from the losing side of a resolved market
+    FeesAccrued {
+        market_id: MarketId,
+        protocol_fee: Balance,
+        creator: AccountId,
+        creator_fee: Balance,
+    },
+    /// Accrued fees were paid out
+    FeesWithdrawn {
+        recipient: AccountId,
+        amount: Balance,
+    },
+    /// The oracle proposed an outcome, final once the dispute window closes
+    ResolutionProposed {
+        market_id: MarketId,
+        outcome: Outcome,
+        dispute_ends_at: BlockNumber,
+    },
+    /// A bettor disputed the proposed outcome
+    ResolutionDisputed {
+        market_id: MarketId,
+        account: AccountId,
+        bond: Balance,
+    },
+    /// The second run disagreed with the proposal or failed; the admin must arbitrate
+    DisputeEscalated {
+        market_id: MarketId,
+    },
+    /// A dispute was settled; the bond was returned if upheld and slashed otherwise
+    DisputeSettled {
+        market_id: MarketId,
+        account: AccountId,
+        upheld: bool,
+    },
+    /// The resolvers disagreed without reaching quorum; the arbitrator must settle the market
+    ResolutionEscalated {
+        market_id: MarketId,
+    },
+    /// A resolver answered below the market's confidence threshold
+    LowConfidenceResult {
+        market_id: MarketId,
+        request_id: u64,
+        confidence_pct: u8,
+    },
+    /// The market was cancelled before resolution and stakes became refundable
+    MarketCancelled {
+        market_id: MarketId,
+        cancelled_by: AccountId,
+    },
+    /// Shares were bought from an LMSR market maker
+    SharesBought {
+        market_id: MarketId,
+        account: AccountId,
+        option_index: OptionIndex,
+        shares: Balance,
+        cost: Balance,
+    },
+    /// Shares were sold back to an LMSR market maker
+    SharesSold {
+        market_id: MarketId,
+        account: AccountId,
+        option_index: OptionIndex,
+        shares: Balance,
+        proceeds: Balance,
+    },
+    /// A settled LMSR market's funds beyond what its shares can claim went back to the creator
+    LiquidityReturned {
+        market_id: MarketId,
+        creator: AccountId,
+        amount: Balance,
+    },
+    /// Part of a bet was withdrawn before resolution
+    BetWithdrawn {
+        market_id: MarketId,
+        account: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+        penalty: Balance,
+    },
+    /// Shares of an option moved from one position to another
+    PositionTransferred {
+        market_id: MarketId,
+        from: AccountId,
+        to: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    },
+    /// An owner set how many shares of an option a spender may transfer for them
+    Approval {
+        market_id: MarketId,
+        owner: AccountId,
+        spender: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    },
+    /// A market's minimum resolver confidence was overridden (`None` restores the global one)
+    MarketMinConfidenceChanged {
+        market_id: MarketId,
+        changed_by: AccountId,
+        min_confidence_pct: Option<u8>,
+    },
+    /// A market's bet limits were overridden (`None` restores the global ones)
+    MarketBetLimitsChanged {
+        market_id: MarketId,
+        changed_by: AccountId,
+        bet_limits: Option<BetLimits>,
+    },
+}
+
+/// Topic tag for market topics
+const MARKET_TOPIC_TAG: u8 = 0x01;
+
+/// Topic identifying a market: tag byte followed by the little-endian market ID
+pub fn market_topic(market_id: MarketId) -> [u8; 32] {
+    let mut topic = [0u8; 32];
+    topic[0] = MARKET_TOPIC_TAG;
+    topic[1..9].copy_from_slice(&market_id.to_le_bytes());
+    topic
+}
+
+impl Event {
+    /// Topics to index this event under: the market, then the account involved
+    pub fn topics(&self) -> Vec<[u8; 32]> {
+        match self {
+            Event::MarketCreated { market_id, creator, .. } => vec![market_topic(*market_id), *creator],
+            Event::BetPlaced { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::ResolutionRequested { market_id, .. } => vec![market_topic(*market_id)],
+            Event::PositionTransferred { market_id, from, to, .. } => vec![market_topic(*market_id), *from, *to],
+            Event::Approval { market_id, owner, spender, .. } => vec![market_topic(*market_id), *owner, *spender],
+            Event::BetWithdrawn { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::SharesBought { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::SharesSold { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::MarketResolved { market_id, .. } => vec![market_topic(*market_id)],
+            Event::ResolutionFailed { market_id, .. } => vec![market_topic(*market_id)],
+            Event::ResolutionEscalated { market_id } => vec![market_topic(*market_id)],
+            Event::LowConfidenceResult { market_id, .. } => vec![market_topic(*market_id)],
+            Event::MarketVoided { market_id } => vec![market_topic(*market_id)],
+            Event::ResolutionProposed { market_id, .. } => vec![market_topic(*market_id)],
+            Event::ResolutionDisputed { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::DisputeEscalated { market_id } => vec![market_topic(*market_id)],
+            Event::DisputeSettled { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::MarketCancelled { market_id, cancelled_by } => vec![market_topic(*market_id), *cancelled_by],
+            Event::PoolSweptToTreasury { market_id, treasury, .. } => vec![market_topic(*market_id), *treasury],
+            Event::RefundClaimed { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::WinningsClaimed { market_id, account, .. } => vec![market_topic(*market_id), *account],
+            Event::FeesAccrued { market_id, creator, .. } => vec![market_topic(*market_id), *creator],
+            Event::LiquidityReturned { market_id, creator, .. } => vec![market_topic(*market_id), *creator],
+            Event::FeesWithdrawn { recipient, .. } => vec![*recipient],
+            Event::ConfigChanged { changed_by, .. } => vec![*changed_by],
+            Event::MarketMinConfidenceChanged { market_id, changed_by, .. } => {
+                vec![market_topic(*market_id), *changed_by]
+            }
+            Event::MarketBetLimitsChanged { market_id, changed_by, .. } => vec![market_topic(*market_id), *changed_by],
+        }
+    }
 }
 
 // ============================================================================
@@ -177,12 +932,88 @@ pub struct ContractAgentRequest {
 }
 
 /// Receipt returned from chain extension
-#[derive(Clone, Encode, Decode, TypeInfo, Debug)]
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
 pub struct AgentRunReceipt {
     pub request_id: u64,
     pub estimated_start_block: BlockNumber,
 }
 
+/// What a resolver run has reported so far
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub enum RunStatus {
+    /// The callback has not arrived yet
+    Outstanding,
+    /// The run reported this outcome
+    Reported(Outcome),
+    /// The run failed or returned an unusable result
+    Failed,
+    /// The run answered with less than the required confidence
+    LowConfidence,
+}
+
+/// One resolver's run in a resolution round
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct ResolverRun {
+    /// Resolver agent the request was sent to
+    pub resolver: AccountId,
+    /// Receipt returned by the chain extension for the run
+    pub receipt: AgentRunReceipt,
+    /// What the run has reported
+    pub status: RunStatus,
+}
+
+/// Outstanding resolution round for a market: one run per resolver
+#[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct PendingResolution {
+    /// Runs requested in this round, in resolver order
+    pub runs: Vec<ResolverRun>,
+    /// Resolvers whose request could not be submitted; they count as failed runs
+    pub unsubmitted: u8,
+    /// Runs that must report the same outcome to settle the market
+    pub quorum: u8,
+    /// Confidence a run needed to count, fixed when the round was requested
+    pub min_confidence_pct: u8,
+    /// What happens if low-confidence answers keep the round from settling
+    pub low_confidence_action: LowConfidenceAction,
+    /// Block after which outstanding runs are considered timed out
+    pub expires_at: BlockNumber,
+}
+
+impl PendingResolution {
+    /// Receipts of the runs in this round
+    pub fn receipts(&self) -> Vec<AgentRunReceipt> {
+        self.runs.iter().map(|run| run.receipt.clone()).collect()
+    }
+
+    /// Number of runs that reported `outcome`
+    fn votes(&self, outcome: Outcome) -> usize {
+        self.runs.iter().filter(|run| run.status == RunStatus::Reported(outcome)).count()
+    }
+
+    /// Outcome reported by at least `quorum` runs
+    pub fn quorum_outcome(&self) -> Option<Outcome> {
+        self.runs.iter().find_map(|run| match run.status {
+            RunStatus::Reported(outcome) if self.votes(outcome) >= self.quorum as usize => Some(outcome),
+            _ => None,
+        })
+    }
+
+    /// Check if the outstanding runs can still bring some outcome to quorum
+    pub fn can_reach_quorum(&self) -> bool {
+        let outstanding = self.runs.iter().filter(|run| run.status == RunStatus::Outstanding).count();
+        let leading = self.runs.iter().filter_map(|run| match run.status {
+            RunStatus::Reported(outcome) => Some(self.votes(outcome)),
+            _ => None,
+        }).max().unwrap_or(0);
+        leading + outstanding >= self.quorum as usize
+    }
+
+    /// Check if any run in this round ended with `status`
+    pub fn any(&self, status: RunStatus) -> bool {
+        self.runs.iter().any(|run| run.status == status)
+    }
+}
+
 /// Payload delivered in callback
 #[derive(Clone, Encode, Decode, TypeInfo, Debug)]
 pub struct AgentCallbackPayload {
@@ -198,608 +1029,4907 @@ pub struct ResolutionResult {
     pub market_id: MarketId,
     /// Index of the winning option (0-based)
     pub winning_option: OptionIndex,
+    /// The question is ambiguous or the event was cancelled; `winning_option`
+    /// is ignored and the market is voided
+    pub void: bool,
     /// Confidence percentage (0-100)
     pub confidence_pct: u8,
     /// Summary of evidence used
     pub evidence_summary: String,
 }
 
+impl ResolutionResult {
+    /// Outcome this result settles the market with
+    pub fn outcome(&self) -> Outcome {
+        if self.void {
+            Outcome::Void
+        } else {
+            Outcome::Winner(self.winning_option)
+        }
+    }
+}
+
 // ============================================================================
 // Contract Storage
 // ============================================================================
 
+/// Outgoing transfer of contract funds
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, Debug)]
+pub struct Transfer {
+    /// Recipient account
+    pub to: AccountId,
+    /// Amount to send
+    pub amount: Balance,
+}
+
 /// Contract storage layout
-#[derive(Default)]
-pub struct PredictionMarket {
+///
+/// `config` and `next_market_id` are small and loaded eagerly. Markets,
+/// positions and pending resolution receipts live in `storage` and are read by key
+/// only when a call needs them (see [`storage::keys`]).
+pub struct PredictionMarket<S: Storage = MemoryStorage> {
     /// Contract configuration
     pub config: Config,
     /// Next market ID to assign
     pub next_market_id: MarketId,
-    /// All markets by ID
-    pub markets: Vec<(MarketId, Market)>,
-    /// User positions: (market_id, account) -> Position
-    pub positions: Vec<((MarketId, AccountId), Position)>,
-    /// Pending resolution requests: market_id -> request_id
-    pub pending_resolutions: Vec<(MarketId, u64)>,
+    /// Backing storage for markets, positions and pending resolutions
+    storage: S,
+    /// Events emitted during the current call, deposited by the entry point
+    events: Vec<Event>,
+    /// Payouts made during the current call, executed by the entry point
+    transfers: Vec<Transfer>,
 }
 
 // ============================================================================
 // Contract Implementation
 // ============================================================================
 
-impl PredictionMarket {
+impl PredictionMarket<MemoryStorage> {
+    /// Initialize the contract with an admin, backed by in-memory storage
+    pub fn new(admin: AccountId) -> Self {
+        Self::init(MemoryStorage::default(), admin)
+    }
+}
+
+impl<S: Storage> PredictionMarket<S> {
     // ------------------------------------------------------------------------
     // Constructor
     // ------------------------------------------------------------------------
 
-    /// Initialize the contract with an admin
-    pub fn new(admin: AccountId) -> Self {
-        Self {
+    /// Initialize the contract in `storage` with an admin
+    pub fn init(storage: S, admin: AccountId) -> Self {
+        let mut contract = Self {
             config: Config {
                 admin,
                 market_creator_agent: None,
-                resolver_oracle_agent: None,
+                resolver_agents: Vec::new(),
+                resolver_quorum: 1,
+                agent_callback_origin: None,
+                max_resolution_attempts: DEFAULT_MAX_RESOLUTION_ATTEMPTS,
+                treasury: None,
+                no_winner_policy: NoWinnerPolicy::Refund,
+                betting_cutoff_blocks: 0,
+                protocol_fee_bps: 0,
+                dispute_window_blocks: 0,
+                dispute_bond: 0,
+                min_confidence_pct: 0,
+                low_confidence_action: LowConfidenceAction::ReRequest,
+                exit_penalty_bps: 0,
+                bet_limits: BetLimits::default(),
+                pending_admin: None,
+                config_manager: None,
+                fee_manager: None,
+                pauser: None,
+                arbitrator: None,
+                paused: false,
             },
             next_market_id: 0,
-            markets: Vec::new(),
-            positions: Vec::new(),
-            pending_resolutions: Vec::new(),
-        }
+            storage,
+            events: Vec::new(),
+            transfers: Vec::new(),
+        };
+        contract.storage.set(&keys::next_market_id(), &contract.next_market_id);
+        contract.config_changed(admin);
+        contract
     }
 
-    // ------------------------------------------------------------------------
-    // Admin Functions
-    // ------------------------------------------------------------------------
+    /// Load a previously initialized contract from `storage`
+    pub fn load(storage: S) -> Option<Self> {
+        let config = storage.get(&keys::config())?;
+        let next_market_id = storage.get(&keys::next_market_id()).unwrap_or_default();
+        Some(Self {
+            config,
+            next_market_id,
+            storage,
+            events: Vec::new(),
+            transfers: Vec::new(),
+        })
+    }
 
-    /// Set the market creator agent address (admin only)
-    pub fn set_market_creator(&mut self, caller: AccountId, agent_id: AccountId) -> Result<(), &'static str> {
-        if caller != self.config.admin {
-            return Err("Only admin can set market creator");
-        }
-        self.config.market_creator_agent = Some(agent_id);
+    /// Backing storage
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Consume the contract, returning its backing storage
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Drain the events emitted since the last call to this method
+    pub fn take_events(&mut self) -> Vec<Event> {
+        core::mem::take(&mut self.events)
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Drain the transfers made since the last call to this method
+    ///
+    /// The entry point executes them once the call has succeeded.
+    pub fn take_transfers(&mut self) -> Vec<Transfer> {
+        core::mem::take(&mut self.transfers)
+    }
+
+    /// Pay `amount` out of `market`'s escrow to `to`
+    fn pay_out(&mut self, market: &mut Market, to: AccountId, amount: Balance) -> Result<(), Error> {
+        self.release_escrow(market, amount)?;
+        self.transfers.push(Transfer { to, amount });
         Ok(())
     }
 
-    /// Set the resolver oracle agent address (admin only)
-    pub fn set_resolver_oracle(&mut self, caller: AccountId, agent_id: AccountId) -> Result<(), &'static str> {
-        if caller != self.config.admin {
-            return Err("Only admin can set resolver oracle");
-        }
-        self.config.resolver_oracle_agent = Some(agent_id);
+    /// Remove `amount` from `market`'s escrow without paying it out
+    fn release_escrow(&mut self, market: &mut Market, amount: Balance) -> Result<(), Error> {
+        let escrowed = market.escrowed.checked_sub(amount).ok_or(Error::Overflow)?;
+        let total = self.total_escrowed().checked_sub(amount).ok_or(Error::Overflow)?;
+        market.escrowed = escrowed;
+        self.storage.set(&keys::total_escrowed(), &total);
         Ok(())
     }
 
-    // ------------------------------------------------------------------------
-    // Market Lifecycle
-    // ------------------------------------------------------------------------
+    /// Add `amount` to the protocol fees awaiting withdrawal to the treasury
+    fn accrue_protocol_fees(&mut self, amount: Balance) -> Result<(), Error> {
+        let accrued = self.get_accrued_fees().checked_add(amount).ok_or(Error::Overflow)?;
+        self.storage.set(&keys::accrued_fees(), &accrued);
+        Ok(())
+    }
 
-    /// Create a new prediction market (Market Creator Agent only)
-    /// 
-    /// For binary markets, use options = ["Yes", "No"]
-    pub fn create_market(
-        &mut self,
-        caller: AccountId,
-        question: String,
-        options: Vec<String>,
-        resolution_criteria: String,
-        resolution_source: String,
-        resolution_deadline: BlockNumber,
-    ) -> Result<MarketId, &'static str> {
-        // Access control: only market creator agent
-        let creator_agent = self.config.market_creator_agent
-            .ok_or("Market creator agent not configured")?;
-        
-        if caller != creator_agent {
-            return Err("Only market creator agent can create markets");
-        }
+    /// Take `amount` received with the current call into `market`'s escrow
+    fn escrow(&mut self, market: &mut Market, amount: Balance) -> Result<(), Error> {
+        let escrowed = market.escrowed.checked_add(amount).ok_or(Error::Overflow)?;
+        let total = self.total_escrowed().checked_add(amount).ok_or(Error::Overflow)?;
+        market.escrowed = escrowed;
+        self.storage.set(&keys::total_escrowed(), &total);
+        Ok(())
+    }
 
-        // Validate options
-        if options.len() < 2 {
-            return Err("Market must have at least 2 options");
-        }
-        if options.len() > MAX_OPTIONS {
-            return Err("Too many options");
+    fn total_escrowed(&self) -> Balance {
+        self.storage.get(&keys::total_escrowed()).unwrap_or_default()
+    }
+
+    /// Move a resolved market's fees out of its escrow into the accrued balances
+    fn accrue_fees(&mut self, market: &mut Market) -> Result<(), Error> {
+        let (protocol_fee, creator_fee) = market.fees()?;
+        if protocol_fee == 0 && creator_fee == 0 {
+            return Ok(());
         }
+        let creator_accrued = self.get_creator_fees(market.creator).checked_add(creator_fee).ok_or(Error::Overflow)?;
+        self.release_escrow(market, protocol_fee.checked_add(creator_fee).ok_or(Error::Overflow)?)?;
+        self.accrue_protocol_fees(protocol_fee)?;
+        self.storage.set(&keys::creator_fees(&market.creator), &creator_accrued);
 
-        let market_id = self.next_market_id;
-        self.next_market_id += 1;
+        self.emit(Event::FeesAccrued {
+            market_id: market.id,
+            protocol_fee,
+            creator: market.creator,
+            creator_fee,
+        });
+        Ok(())
+    }
 
-        let num_options = options.len();
-        let market = Market {
-            id: market_id,
-            question,
-            options,
-            resolution_criteria,
-            resolution_source,
-            creator: caller,
-            resolution_deadline,
-            shares_per_option: vec![0; num_options],
-            status: MarketStatus::Open,
-            winning_option: None,
+    /// Pay a settled LMSR market's funds beyond what its shares can claim back to its creator
+    fn return_liquidity(&mut self, market: &mut Market) -> Result<(), Error> {
+        let Pricing::Lmsr { liquidity } = market.pricing else {
+            return Ok(());
+        };
+        let owed = match market.winning_option {
+            Some(idx) if market.status == MarketStatus::Resolved => market.shares_per_option[idx as usize],
+            _ => {
+                // Refunds redeem each share at its final price
+                let prices = lmsr::prices(&market.shares_per_option, liquidity);
+                let owed = market
+                    .shares_per_option
+                    .iter()
+                    .zip(&prices)
+                    .map(|(&shares, &price)| math::mul_div_ceil(shares, price, lmsr::ONE).ok_or(Error::Overflow))
+                    .collect::<Result<Vec<_>, _>>()?;
+                math::checked_sum(&owed)?
+            }
         };
+        let amount = market.escrowed.saturating_sub(owed);
+        if amount == 0 {
+            return Ok(());
+        }
 
-        self.markets.push((market_id, market));
-        Ok(market_id)
+        let creator = market.creator;
+        self.pay_out(market, creator, amount)?;
+        self.emit(Event::LiquidityReturned {
+            market_id: market.id,
+            creator,
+            amount,
+        });
+        Ok(())
     }
 
-    /// Place a bet on a specific option
-    pub fn place_bet(
-        &mut self,
-        caller: AccountId,
-        market_id: MarketId,
-        option_index: OptionIndex,
-        amount: Balance,
-    ) -> Result<(), &'static str> {
-        // Find market
-        let market = self.markets.iter_mut()
-            .find(|(id, _)| *id == market_id)
-            .map(|(_, m)| m)
-            .ok_or("Market not found")?;
+    fn config_changed(&mut self, changed_by: AccountId) {
+        self.save_config();
+        self.emit(Event::ConfigChanged {
+            changed_by,
+            config: Box::new(self.config.clone()),
+        });
+    }
 
-        // Check market is open
-        if market.status != MarketStatus::Open {
-            return Err("Market is not open for betting");
-        }
+    // ------------------------------------------------------------------------
+    // Storage Access
+    // ------------------------------------------------------------------------
 
-        // Validate option index
-        let idx = option_index as usize;
-        if idx >= market.options.len() {
-            return Err("Invalid option index");
-        }
+    fn save_config(&mut self) {
+        self.storage.set(&keys::config(), &self.config);
+    }
 
-        // Update market totals
-        market.shares_per_option[idx] += amount;
+    fn load_market(&self, market_id: MarketId) -> Result<Market, Error> {
+        self.storage.get(&keys::market(market_id)).ok_or(Error::MarketNotFound)
+    }
 
-        // Update user position
-        let key = (market_id, caller);
-        let position = self.positions.iter_mut()
-            .find(|(k, _)| *k == key)
-            .map(|(_, p)| p);
-
-        match position {
-            Some(pos) => {
-                // Ensure position has right size
-                while pos.shares.len() < market.options.len() {
-                    pos.shares.push(0);
-                }
-                pos.shares[idx] += amount;
-            }
-            None => {
-                let mut new_pos = Position::new(market.options.len());
-                new_pos.shares[idx] = amount;
-                self.positions.push((key, new_pos));
-            }
+    fn save_market(&mut self, market: &Market) {
+        self.storage.set(&keys::market(market.id), market);
+    }
+
+    fn load_position(&self, market_id: MarketId, account: &AccountId) -> Option<Position> {
+        self.storage.get(&keys::position(market_id, account))
+    }
+
+    /// Store `position`, removing it once it holds no shares
+    fn save_position(&mut self, market_id: MarketId, account: &AccountId, position: &Position) {
+        if position.is_empty() {
+            self.storage.clear(&keys::position(market_id, account));
+        } else {
+            self.storage.set(&keys::position(market_id, account), position);
         }
+    }
+
+    // ------------------------------------------------------------------------
+    // Admin Functions
+    // ------------------------------------------------------------------------
 
+    /// Set the market creator agent address (config manager)
+    pub fn set_market_creator(&mut self, caller: AccountId, agent_id: AccountId) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        self.config.market_creator_agent = Some(agent_id);
+        self.config_changed(caller);
         Ok(())
     }
 
-    /// Request market resolution (anyone can call after deadline)
-    /// Returns the agent request to be sent via chain extension
-    pub fn request_resolution(
-        &mut self,
-        market_id: MarketId,
-        current_block: BlockNumber,
-    ) -> Result<ContractAgentRequest, &'static str> {
-        // Find market
-        let market = self.markets.iter_mut()
-            .find(|(id, _)| *id == market_id)
-            .map(|(_, m)| m)
-            .ok_or("Market not found")?;
+    /// Set a single resolver oracle agent that decides markets alone (admin only)
+    pub fn set_resolver_oracle(&mut self, caller: AccountId, agent_id: AccountId) -> Result<(), Error> {
+        self.set_resolvers(caller, vec![agent_id], 1)
+    }
 
-        // Check deadline passed
-        if current_block < market.resolution_deadline {
-            return Err("Resolution deadline not reached");
+    /// Set the resolver oracle agents and how many must agree (admin only)
+    ///
+    /// `quorum` must be a majority of `agents`, so at most one outcome can
+    /// reach it. Rounds already requested keep the quorum they started with.
+    /// Not delegated to the config manager: whoever picks the resolvers
+    /// picks the outcomes, which would bypass the arbitrator and the admin.
+    pub fn set_resolvers(&mut self, caller: AccountId, agents: Vec<AccountId>, quorum: u8) -> Result<(), Error> {
+        if caller != self.config.admin {
+            return Err(Error::NotAuthorized);
         }
-
-        // Check not already pending/resolved
-        if market.status != MarketStatus::Open {
-            return Err("Market is not open");
+        if agents.is_empty() || agents.len() > MAX_RESOLVERS {
+            return Err(Error::InvalidConfig);
         }
+        if agents.iter().enumerate().any(|(i, agent)| agents[..i].contains(agent)) {
+            return Err(Error::InvalidConfig);
+        }
+        if quorum as usize > agents.len() || (quorum as usize) * 2 <= agents.len() {
+            return Err(Error::InvalidConfig);
+        }
+        self.config.resolver_agents = agents;
+        self.config.resolver_quorum = quorum;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Get resolver agent
-        let resolver = self.config.resolver_oracle_agent
-            .ok_or("Resolver oracle not configured")?;
-
-        // Update status
-        market.status = MarketStatus::PendingResolution;
-
-        // Build resolution request
-        let request_input = MarketResolutionRequest {
-            market_id,
-            question: market.question.clone(),
-            options: market.options.clone(),
-            resolution_criteria: market.resolution_criteria.clone(),
-            resolution_source: market.resolution_source.clone(),
-        };
+    /// Set how many resolver runs a market gets before the admin fallback (config manager)
+    pub fn set_max_resolution_attempts(&mut self, caller: AccountId, attempts: u8) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        if attempts == 0 {
+            return Err(Error::InvalidConfig);
+        }
+        self.config.max_resolution_attempts = attempts;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Build chain extension request
-        Ok(ContractAgentRequest {
-            target_agent: resolver,
-            input: request_input.encode(),
-            ttl_blocks: 100, // ~10 minutes at 6s blocks
-            callback: Some(CallbackSpec {
-                selector: [0x04, 0x00, 0x00, 0x01], // on_resolution_complete
-                gas_limit: 1_000_000_000, // 1B gas for settlement
-            }),
-        })
+    /// Set the treasury account (admin only)
+    ///
+    /// Deliberately not delegated to the fee manager: with both this and
+    /// `withdraw_fees` it could send the accrued fees to itself.
+    pub fn set_treasury(&mut self, caller: AccountId, treasury: AccountId) -> Result<(), Error> {
+        if caller != self.config.admin {
+            return Err(Error::NotAuthorized);
+        }
+        self.config.treasury = Some(treasury);
+        self.config_changed(caller);
+        Ok(())
     }
 
-    /// Handle resolution callback from oracle agent
-    pub fn on_resolution_complete(
-        &mut self,
-        callback_payload: AgentCallbackPayload,
-    ) -> Result<(), &'static str> {
-        if !callback_payload.success {
-            // Agent failed - could implement retry logic here
-            return Err("Oracle agent failed to resolve");
+    /// Set how markets nobody won are settled from now on (config manager)
+    ///
+    /// Markets already resolved keep the policy in force when they resolved.
+    pub fn set_no_winner_policy(&mut self, caller: AccountId, policy: NoWinnerPolicy) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        if policy == NoWinnerPolicy::Treasury && self.config.treasury.is_none() {
+            return Err(Error::TreasuryNotConfigured);
         }
+        self.config.no_winner_policy = policy;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Decode resolution result
-        let result = ResolutionResult::decode(&mut &callback_payload.output[..])
-            .map_err(|_| "Failed to decode resolution result")?;
+    /// Set how many blocks before the deadline betting closes on new markets (config manager)
+    ///
+    /// Existing markets keep the cutoff they were created with.
+    pub fn set_betting_cutoff(&mut self, caller: AccountId, blocks: BlockNumber) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        self.config.betting_cutoff_blocks = blocks;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Find market
-        let market = self.markets.iter_mut()
-            .find(|(id, _)| *id == result.market_id)
-            .map(|(_, m)| m)
-            .ok_or("Market not found")?;
+    /// Set the protocol fee charged on new markets, in basis points (fee manager)
+    ///
+    /// Existing markets keep the fee they were created with. Fees accrue to
+    /// the treasury, so one must be configured before a fee can be charged.
+    pub fn set_protocol_fee(&mut self, caller: AccountId, fee_bps: u16) -> Result<(), Error> {
+        self.check_role(caller, Role::FeeManager)?;
+        if fee_bps > MAX_PROTOCOL_FEE_BPS {
+            return Err(Error::FeeTooHigh);
+        }
+        if fee_bps > 0 && self.config.treasury.is_none() {
+            return Err(Error::TreasuryNotConfigured);
+        }
+        self.config.protocol_fee_bps = fee_bps;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Verify market is pending
-        if market.status != MarketStatus::PendingResolution {
-            return Err("Market is not pending resolution");
+    /// Set the penalty for withdrawing a bet from new markets, in basis points (fee manager)
+    ///
+    /// Existing markets keep the penalty they were created with. Penalties
+    /// accrue to the treasury, so one must be configured before a penalty
+    /// can be charged.
+    pub fn set_exit_penalty(&mut self, caller: AccountId, penalty_bps: u16) -> Result<(), Error> {
+        self.check_role(caller, Role::FeeManager)?;
+        if penalty_bps > MAX_EXIT_PENALTY_BPS {
+            return Err(Error::FeeTooHigh);
+        }
+        if penalty_bps > 0 && self.config.treasury.is_none() {
+            return Err(Error::TreasuryNotConfigured);
         }
+        self.config.exit_penalty_bps = penalty_bps;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Validate winning option
-        if result.winning_option as usize >= market.options.len() {
-            return Err("Invalid winning option index");
+    /// Send the accrued protocol fees to the treasury (fee manager)
+    pub fn withdraw_fees(&mut self, caller: AccountId) -> Result<Balance, Error> {
+        self.check_role(caller, Role::FeeManager)?;
+        let treasury = self.config.treasury.ok_or(Error::TreasuryNotConfigured)?;
+        let amount = self.get_accrued_fees();
+        if amount == 0 {
+            return Err(Error::NoFeesAccrued);
         }
 
-        // Apply resolution
-        market.status = MarketStatus::Resolved;
-        market.winning_option = Some(result.winning_option);
+        self.storage.clear(&keys::accrued_fees());
+        self.transfers.push(Transfer { to: treasury, amount });
+        self.emit(Event::FeesWithdrawn {
+            recipient: treasury,
+            amount,
+        });
+        Ok(amount)
+    }
 
-        // Remove from pending
-        self.pending_resolutions.retain(|(id, _)| *id != result.market_id);
+    /// Set the dispute window and bond for oracle outcomes (config manager)
+    ///
+    /// With a window of 0 blocks oracle outcomes are final at once. Otherwise
+    /// the bond must be non-zero, so disputes are never free. Outcomes
+    /// already proposed keep the window they were proposed with.
+    pub fn set_dispute_window(&mut self, caller: AccountId, blocks: BlockNumber, bond: Balance) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        if blocks > 0 && bond == 0 {
+            return Err(Error::InvalidConfig);
+        }
+        self.config.dispute_window_blocks = blocks;
+        self.config.dispute_bond = bond;
+        self.config_changed(caller);
+        Ok(())
+    }
 
+    /// Set the minimum resolver confidence and what happens below it (config manager)
+    ///
+    /// Rounds already requested keep the settings they started with. Only the
+    /// admin may change the action, or the threshold while the action is
+    /// [`LowConfidenceAction::Void`]: a high threshold then voids markets,
+    /// which would let the config manager pick outcomes.
+    pub fn set_min_confidence(
+        &mut self,
+        caller: AccountId,
+        min_confidence_pct: u8,
+        action: LowConfidenceAction,
+    ) -> Result<(), Error> {
+        if action != self.config.low_confidence_action {
+            if caller != self.config.admin {
+                return Err(Error::NotAuthorized);
+            }
+        } else {
+            self.check_confidence_manager(caller)?;
+        }
+        if min_confidence_pct > 100 {
+            return Err(Error::InvalidConfig);
+        }
+        self.config.min_confidence_pct = min_confidence_pct;
+        self.config.low_confidence_action = action;
+        self.config_changed(caller);
         Ok(())
     }
 
-    /// Claim winnings from a resolved market
-    pub fn claim_winnings(
+    /// Override the minimum resolver confidence for one open market (config manager)
+    ///
+    /// Fixed once resolution is requested. `None` falls back to the global threshold. Admin only while the
+    /// low-confidence action is [`LowConfidenceAction::Void`].
+    pub fn set_market_min_confidence(
         &mut self,
         caller: AccountId,
         market_id: MarketId,
-    ) -> Result<Balance, &'static str> {
-        // Find market
-        let market = self.markets.iter()
-            .find(|(id, _)| *id == market_id)
-            .map(|(_, m)| m)
-            .ok_or("Market not found")?;
-
-        // Check resolved
-        if market.status != MarketStatus::Resolved {
-            return Err("Market not resolved");
+        min_confidence_pct: Option<u8>,
+    ) -> Result<(), Error> {
+        self.check_confidence_manager(caller)?;
+        if min_confidence_pct.is_some_and(|pct| pct > 100) {
+            return Err(Error::InvalidConfig);
         }
+        let mut market = self.load_market(market_id)?;
+        if market.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        market.min_confidence_pct = min_confidence_pct;
+        self.save_market(&market);
+        self.emit(Event::MarketMinConfidenceChanged {
+            market_id,
+            changed_by: caller,
+            min_confidence_pct,
+        });
+        Ok(())
+    }
 
-        let winning_idx = market.winning_option.ok_or("No winning option set")? as usize;
-
-        // Find user position
-        let key = (market_id, caller);
-        let position_idx = self.positions.iter()
-            .position(|(k, _)| *k == key)
-            .ok_or("No position in this market")?;
-
-        let (_, position) = &self.positions[position_idx];
+    /// Set the bet limits for markets without their own (config manager)
+    pub fn set_bet_limits(&mut self, caller: AccountId, limits: BetLimits) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        limits.validate()?;
+        self.config.bet_limits = limits;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Check user has shares in winning option
-        if winning_idx >= position.shares.len() {
-            return Err("No winning shares");
+    /// Override the bet limits for one open market (config manager)
+    ///
+    /// `None` falls back to the global limits.
+    pub fn set_market_bet_limits(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        limits: Option<BetLimits>,
+    ) -> Result<(), Error> {
+        self.check_role(caller, Role::ConfigManager)?;
+        if let Some(limits) = &limits {
+            limits.validate()?;
         }
-
-        let winning_shares = position.shares[winning_idx];
-        if winning_shares == 0 {
-            return Err("No winning shares");
+        let mut market = self.load_market(market_id)?;
+        if market.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
         }
+        market.bet_limits = limits;
+        self.save_market(&market);
+        self.emit(Event::MarketBetLimitsChanged {
+            market_id,
+            changed_by: caller,
+            bet_limits: limits,
+        });
+        Ok(())
+    }
 
-        // Calculate payout: winner gets proportional share of total pool
-        // Payout = (user_shares / winning_pool) * total_pool
-        let total_pool = market.total_pool();
-        let winning_pool = market.shares_per_option[winning_idx];
-
-        if winning_pool == 0 {
-            return Err("No shares in winning option");
+    /// Set the account the runtime delivers agent callbacks from (admin only)
+    ///
+    /// Not delegated to the config manager: this origin is all that
+    /// authenticates `on_resolution_complete`, so setting it to its own
+    /// account would let it forge the outcome of any pending request.
+    pub fn set_callback_origin(&mut self, caller: AccountId, origin: AccountId) -> Result<(), Error> {
+        if caller != self.config.admin {
+            return Err(Error::NotAuthorized);
         }
+        self.config.agent_callback_origin = Some(origin);
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        let payout = (winning_shares as u128 * total_pool as u128) / winning_pool as u128;
+    /// Pause or unpause market creation, betting and share purchases (pauser)
+    ///
+    /// Withdrawals, sales, resolution and claims carry on while paused, so
+    /// funds are never locked in.
+    pub fn set_paused(&mut self, caller: AccountId, paused: bool) -> Result<(), Error> {
+        self.check_role(caller, Role::Pauser)?;
+        self.config.paused = paused;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        // Remove position (claimed)
-        self.positions.remove(position_idx);
+    /// Give `role` to `account`, or take it back with `None` (admin only)
+    ///
+    /// The admin keeps every role regardless.
+    pub fn set_role(&mut self, caller: AccountId, role: Role, account: Option<AccountId>) -> Result<(), Error> {
+        if caller != self.config.admin {
+            return Err(Error::NotAuthorized);
+        }
+        *self.role_holder_mut(role) = account;
+        self.config_changed(caller);
+        Ok(())
+    }
 
-        Ok(payout as Balance)
+    /// Propose `new_admin` as the next admin, or withdraw a proposal with `None` (admin only)
+    ///
+    /// The current admin stays in charge until the proposed account calls
+    /// `accept_admin`, so a mistyped address cannot lock the contract.
+    pub fn propose_admin(&mut self, caller: AccountId, new_admin: Option<AccountId>) -> Result<(), Error> {
+        if caller != self.config.admin {
+            return Err(Error::NotAuthorized);
+        }
+        self.config.pending_admin = new_admin;
+        self.config_changed(caller);
+        Ok(())
     }
 
-    // ------------------------------------------------------------------------
-    // View Functions
-    // ------------------------------------------------------------------------
+    /// Take over as admin after being proposed with `propose_admin`
+    ///
+    /// Roles the previous admin gave out are taken back, so the new admin
+    /// starts with no delegates it did not choose.
+    pub fn accept_admin(&mut self, caller: AccountId) -> Result<(), Error> {
+        if self.config.pending_admin != Some(caller) {
+            return Err(Error::NotAuthorized);
+        }
+        self.config.admin = caller;
+        self.config.pending_admin = None;
+        for role in [Role::ConfigManager, Role::FeeManager, Role::Pauser, Role::Arbitrator] {
+            *self.role_holder_mut(role) = None;
+        }
+        self.config_changed(caller);
+        Ok(())
+    }
 
-    /// Get market details
-    pub fn get_market(&self, market_id: MarketId) -> Option<&Market> {
-        self.markets.iter()
-            .find(|(id, _)| *id == market_id)
-            .map(|(_, m)| m)
+    /// Whether `account` holds `role`, either as the admin or by delegation
+    pub fn has_role(&self, account: AccountId, role: Role) -> bool {
+        account == self.config.admin || self.role_holder(role) == Some(account)
     }
 
-    /// Get user position in a market
-    pub fn get_position(&self, market_id: MarketId, account: AccountId) -> Position {
-        let key = (market_id, account);
-        self.positions.iter()
-            .find(|(k, _)| *k == key)
-            .map(|(_, p)| p.clone())
-            .unwrap_or_default()
+    fn check_role(&self, caller: AccountId, role: Role) -> Result<(), Error> {
+        if !self.has_role(caller, role) {
+            return Err(Error::NotAuthorized);
+        }
+        Ok(())
     }
 
-    /// Get contract configuration
-    pub fn get_config(&self) -> &Config {
-        &self.config
+    /// Check that `caller` may change confidence thresholds under the current action
+    fn check_confidence_manager(&self, caller: AccountId) -> Result<(), Error> {
+        match self.config.low_confidence_action {
+            LowConfidenceAction::Void if caller != self.config.admin => Err(Error::NotAuthorized),
+            LowConfidenceAction::Void => Ok(()),
+            _ => self.check_role(caller, Role::ConfigManager),
+        }
     }
 
-    /// Get implied odds for each option (based on current shares)
-    /// Returns percentages that sum to 100
-    pub fn get_implied_odds(&self, market_id: MarketId) -> Option<Vec<u8>> {
-        let market = self.get_market(market_id)?;
-        let total = market.total_pool();
-        
-        if total == 0 {
-            // Equal odds when no bets
-            let equal = 100 / market.options.len() as u8;
-            return Some(vec![equal; market.options.len()]);
+    /// Account `role` is delegated to, if any
+    fn role_holder(&self, role: Role) -> Option<AccountId> {
+        match role {
+            Role::ConfigManager => self.config.config_manager,
+            Role::FeeManager => self.config.fee_manager,
+            Role::Pauser => self.config.pauser,
+            Role::Arbitrator => self.config.arbitrator,
         }
+    }
 
-        Some(
-            market.shares_per_option
-                .iter()
-                .map(|&shares| ((shares * 100) / total) as u8)
-                .collect()
-        )
+    fn role_holder_mut(&mut self, role: Role) -> &mut Option<AccountId> {
+        match role {
+            Role::ConfigManager => &mut self.config.config_manager,
+            Role::FeeManager => &mut self.config.fee_manager,
+            Role::Pauser => &mut self.config.pauser,
+            Role::Arbitrator => &mut self.config.arbitrator,
+        }
     }
-}
 
-// ============================================================================
-// Entry Point Dispatch (WASM)
-// ============================================================================
+    fn check_not_paused(&self) -> Result<(), Error> {
+        if self.config.paused {
+            return Err(Error::ContractPaused);
+        }
+        Ok(())
+    }
 
-/// Function selectors
-pub mod selectors {
-    pub const CONSTRUCTOR: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
-    pub const SET_MARKET_CREATOR: [u8; 4] = [0x00, 0x00, 0x00, 0x02];
-    pub const SET_RESOLVER_ORACLE: [u8; 4] = [0x00, 0x00, 0x00, 0x03];
-    pub const CREATE_MARKET: [u8; 4] = [0x01, 0x00, 0x00, 0x01];
-    pub const PLACE_BET: [u8; 4] = [0x02, 0x00, 0x00, 0x01];
-    pub const REQUEST_RESOLUTION: [u8; 4] = [0x03, 0x00, 0x00, 0x01];
-    pub const ON_RESOLUTION_COMPLETE: [u8; 4] = [0x04, 0x00, 0x00, 0x01];
-    pub const CLAIM_WINNINGS: [u8; 4] = [0x05, 0x00, 0x00, 0x01];
-    pub const GET_MARKET: [u8; 4] = [0x06, 0x00, 0x00, 0x01];
-    pub const GET_POSITION: [u8; 4] = [0x07, 0x00, 0x00, 0x01];
-    pub const GET_IMPLIED_ODDS: [u8; 4] = [0x08, 0x00, 0x00, 0x01];
-}
+    // ------------------------------------------------------------------------
+    // Market Lifecycle
+    // ------------------------------------------------------------------------
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Create a new prediction market (Market Creator Agent only)
+    /// 
+    /// For binary markets, use options = ["Yes", "No"]. `creator_fee_bps` is
+    /// the creator's cut of the losing side at resolution (0 for none), on top
+    /// of the protocol fee in force when the market is created.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_market(
+        &mut self,
+        caller: AccountId,
+        question: String,
+        options: Vec<String>,
+        resolution_criteria: String,
+        resolution_source: String,
+        resolution_deadline: BlockNumber,
+        creator_fee_bps: u16,
+    ) -> Result<MarketId, Error> {
+        self.check_new_market(caller, options.len())?;
+        if creator_fee_bps > MAX_CREATOR_FEE_BPS {
+            return Err(Error::FeeTooHigh);
+        }
 
-    fn alice() -> AccountId {
-        [1u8; 32]
+        self.insert_market(
+            caller,
+            question,
+            options,
+            resolution_criteria,
+            resolution_source,
+            resolution_deadline,
+            creator_fee_bps,
+            Pricing::Parimutuel,
+            0,
+        )
     }
 
-    fn bob() -> AccountId {
-        [2u8; 32]
-    }
+    /// Create a market priced by an LMSR market maker (Market Creator Agent only)
+    ///
+    /// `transferred` is the value sent with the call. It must cover the
+    /// market maker's worst-case loss, `lmsr::subsidy(options, liquidity)`,
+    /// and any excess is returned. Whatever the shares cannot claim once the
+    /// market settles goes back to the creator. LMSR markets charge no fees.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_lmsr_market(
+        &mut self,
+        caller: AccountId,
+        question: String,
+        options: Vec<String>,
+        resolution_criteria: String,
+        resolution_source: String,
+        resolution_deadline: BlockNumber,
+        liquidity: Balance,
+        transferred: Balance,
+    ) -> Result<MarketId, Error> {
+        self.check_new_market(caller, options.len())?;
+        let subsidy = lmsr::subsidy(options.len(), liquidity)?;
+        if transferred < subsidy {
+            return Err(Error::StakeMismatch);
+        }
 
-    fn charlie() -> AccountId {
-        [3u8; 32]
+        let market_id = self.insert_market(
+            caller,
+            question,
+            options,
+            resolution_criteria,
+            resolution_source,
+            resolution_deadline,
+            0,
+            Pricing::Lmsr { liquidity },
+            subsidy,
+        )?;
+        if transferred > subsidy {
+            self.transfers.push(Transfer { to: caller, amount: transferred - subsidy });
+        }
+        Ok(market_id)
     }
 
-    fn market_creator() -> AccountId {
-        [10u8; 32]
-    }
+    /// Check that `caller` may create a market with `num_options` options
+    fn check_new_market(&self, caller: AccountId, num_options: usize) -> Result<(), Error> {
+        self.check_not_paused()?;
 
-    fn resolver_oracle() -> AccountId {
-        [11u8; 32]
-    }
+        // Access control: only market creator agent
+        let creator_agent = self.config.market_creator_agent
+            .ok_or(Error::MarketCreatorNotConfigured)?;
+        
+        if caller != creator_agent {
+            return Err(Error::NotAuthorized);
+        }
 
-    #[test]
-    fn test_constructor() {
-        let contract = PredictionMarket::new(alice());
-        assert_eq!(contract.config.admin, alice());
-        assert!(contract.config.market_creator_agent.is_none());
-        assert!(contract.config.resolver_oracle_agent.is_none());
+        // Validate options
+        if num_options < 2 {
+            return Err(Error::TooFewOptions);
+        }
+        if num_options > MAX_OPTIONS {
+            return Err(Error::TooManyOptions);
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_set_agents() {
-        let mut contract = PredictionMarket::new(alice());
+    /// Store a new market holding `subsidy` in escrow
+    #[allow(clippy::too_many_arguments)]
+    fn insert_market(
+        &mut self,
+        caller: AccountId,
+        question: String,
+        options: Vec<String>,
+        resolution_criteria: String,
+        resolution_source: String,
+        resolution_deadline: BlockNumber,
+        creator_fee_bps: u16,
+        pricing: Pricing,
+        subsidy: Balance,
+    ) -> Result<MarketId, Error> {
+        let market_id = self.next_market_id;
+        let num_options = options.len();
+        let mut market = Market {
+            id: market_id,
+            question,
+            options,
+            resolution_criteria,
+            resolution_source,
+            creator: caller,
+            resolution_deadline,
+            betting_closes_at: resolution_deadline.saturating_sub(self.config.betting_cutoff_blocks),
+            shares_per_option: vec![0; num_options],
+            status: MarketStatus::Open,
+            winning_option: None,
+            escrowed: 0,
+            resolution_attempts: 0,
+            no_winner_policy: None,
+            protocol_fee_bps: match pricing {
+                Pricing::Parimutuel => self.config.protocol_fee_bps,
+                Pricing::Lmsr { .. } => 0,
+            },
+            creator_fee_bps,
+            proposal: None,
+            min_confidence_pct: None,
+            resolution: None,
+            pricing,
+            exit_penalty_bps: match pricing {
+                Pricing::Parimutuel => self.config.exit_penalty_bps,
+                Pricing::Lmsr { .. } => 0,
+            },
+            bet_limits: None,
+            escalated: false,
+            disputed_resolution: None,
+        };
+        self.escrow(&mut market, subsidy)?;
+
+        self.next_market_id += 1;
+        self.storage.set(&keys::next_market_id(), &self.next_market_id);
+        self.save_market(&market);
+        self.emit(Event::MarketCreated {
+            market_id,
+            creator: caller,
+            question: market.question,
+            options: market.options,
+            resolution_deadline,
+        });
+        Ok(market_id)
+    }
+
+    /// Place a bet on a specific option
+    ///
+    /// `transferred` is the value sent with the call and must equal `amount`;
+    /// it is held in escrow until the market pays out. Bets are accepted
+    /// until the market's `betting_closes_at` block.
+    pub fn place_bet(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        option_index: OptionIndex,
+        amount: Balance,
+        transferred: Balance,
+        current_block: BlockNumber,
+    ) -> Result<(), Error> {
+        self.check_not_paused()?;
+        if amount == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        if transferred != amount {
+            return Err(Error::StakeMismatch);
+        }
+
+        // Find market
+        let mut market = self.load_market(market_id)?;
+        if market.pricing != Pricing::Parimutuel {
+            return Err(Error::PricingMismatch);
+        }
+
+        // Check market is open
+        if market.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if current_block >= market.betting_closes_at {
+            return Err(Error::BettingClosed);
+        }
+
+        // Validate option index
+        let idx = option_index as usize;
+        if idx >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        // Check bet limits against the caller's existing stake
+        let mut position = self.load_position(market_id, &caller)
+            .unwrap_or_else(|| Position::new(market.options.len()));
+        market.bet_limits.unwrap_or(self.config.bet_limits)
+            .check(amount, position.total_shares()?)?;
+
+        // Update market totals
+        market.shares_per_option[idx] = market.shares_per_option[idx].checked_add(amount).ok_or(Error::Overflow)?;
+
+        // Update user position
+
+        // Ensure position has right size
+        while position.shares.len() < market.options.len() {
+            position.shares.push(0);
+        }
+        position.shares[idx] = position.shares[idx].checked_add(amount).ok_or(Error::Overflow)?;
+
+        self.escrow(&mut market, amount)?;
+        self.save_market(&market);
+        self.save_position(market_id, &caller, &position);
+        self.emit(Event::BetPlaced {
+            market_id,
+            account: caller,
+            option_index,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw `amount` of a bet on an option while the market is open
+    ///
+    /// Allowed until betting closes. A partial withdrawal must leave at least
+    /// the market's minimum bet on the option. The market's exit penalty is
+    /// taken from `amount` and accrues to the treasury; the rest is paid back.
+    /// Returns the amount paid.
+    pub fn withdraw_bet(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        option_index: OptionIndex,
+        amount: Balance,
+        current_block: BlockNumber,
+    ) -> Result<Balance, Error> {
+        if amount == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        let mut market = self.load_market(market_id)?;
+        if market.pricing != Pricing::Parimutuel {
+            return Err(Error::PricingMismatch);
+        }
+        if market.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if current_block >= market.betting_closes_at {
+            return Err(Error::BettingClosed);
+        }
+        let idx = option_index as usize;
+        if idx >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        let mut position = self.load_position(market_id, &caller).ok_or(Error::NoPosition)?;
+        position.shares[idx] = position.shares[idx].checked_sub(amount).ok_or(Error::InsufficientShares)?;
+        market.bet_limits.unwrap_or(self.config.bet_limits)
+            .check_remaining(position.shares[idx])?;
+        // The pool holds at least every position's stake
+        market.shares_per_option[idx] = market.shares_per_option[idx].checked_sub(amount).ok_or(Error::Overflow)?;
+
+        let penalty = bps_of(amount, market.exit_penalty_bps)?;
+        let payout = amount.checked_sub(penalty).ok_or(Error::Overflow)?;
+        if penalty > 0 {
+            self.release_escrow(&mut market, penalty)?;
+            self.accrue_protocol_fees(penalty)?;
+        }
+        self.pay_out(&mut market, caller, payout)?;
+        self.save_market(&market);
+        self.save_position(market_id, &caller, &position);
+        self.emit(Event::BetWithdrawn {
+            market_id,
+            account: caller,
+            option_index,
+            amount,
+            penalty,
+        });
+
+        Ok(payout)
+    }
+
+    /// Buy `shares` of an option from an LMSR market's market maker
+    ///
+    /// `transferred` is the value sent with the call and is the most the
+    /// caller will pay; the excess over the cost is returned. Returns the
+    /// exact cost, which `quote` gives in advance.
+    pub fn buy_shares(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        option_index: OptionIndex,
+        shares: Balance,
+        transferred: Balance,
+        current_block: BlockNumber,
+    ) -> Result<Balance, Error> {
+        self.check_not_paused()?;
+        let mut market = self.load_market(market_id)?;
+        let liquidity = Self::check_trading(&market, option_index, shares, current_block)?;
+        let idx = option_index as usize;
+
+        let cost = lmsr::buy_cost(&market.shares_per_option, liquidity, idx, shares)?;
+        if cost > transferred {
+            return Err(Error::SlippageExceeded);
+        }
+
+        let mut position = self.load_position(market_id, &caller)
+            .unwrap_or_else(|| Position::new(market.options.len()));
+        position.shares[idx] = position.shares[idx].checked_add(shares).ok_or(Error::Overflow)?;
+        market.shares_per_option[idx] = market.shares_per_option[idx].checked_add(shares).ok_or(Error::Overflow)?;
+
+        self.escrow(&mut market, cost)?;
+        if transferred > cost {
+            self.transfers.push(Transfer { to: caller, amount: transferred - cost });
+        }
+        self.save_market(&market);
+        self.save_position(market_id, &caller, &position);
+        self.emit(Event::SharesBought {
+            market_id,
+            account: caller,
+            option_index,
+            shares,
+            cost,
+        });
+
+        Ok(cost)
+    }
+
+    /// Sell `shares` of an option back to an LMSR market's market maker
+    ///
+    /// Fails with `SlippageExceeded` if the proceeds would be below
+    /// `min_proceeds`. Returns the exact proceeds, which are paid out.
+    pub fn sell_shares(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        option_index: OptionIndex,
+        shares: Balance,
+        min_proceeds: Balance,
+        current_block: BlockNumber,
+    ) -> Result<Balance, Error> {
+        let mut market = self.load_market(market_id)?;
+        let liquidity = Self::check_trading(&market, option_index, shares, current_block)?;
+        let idx = option_index as usize;
+
+        let mut position = self.load_position(market_id, &caller).ok_or(Error::NoPosition)?;
+        position.shares[idx] = position.shares[idx].checked_sub(shares).ok_or(Error::InsufficientShares)?;
+
+        let proceeds = lmsr::sell_proceeds(&market.shares_per_option, liquidity, idx, shares)?;
+        if proceeds < min_proceeds {
+            return Err(Error::SlippageExceeded);
+        }
+        market.shares_per_option[idx] = market.shares_per_option[idx].checked_sub(shares).ok_or(Error::Overflow)?;
+
+        self.pay_out(&mut market, caller, proceeds)?;
+        self.save_market(&market);
+        self.save_position(market_id, &caller, &position);
+        self.emit(Event::SharesSold {
+            market_id,
+            account: caller,
+            option_index,
+            shares,
+            proceeds,
+        });
+
+        Ok(proceeds)
+    }
+
+    /// Check that `market` is an LMSR market taking trades of `shares` of
+    /// `option_index`, returning its liquidity parameter
+    fn check_trading(
+        market: &Market,
+        option_index: OptionIndex,
+        shares: Balance,
+        current_block: BlockNumber,
+    ) -> Result<Balance, Error> {
+        let Pricing::Lmsr { liquidity } = market.pricing else {
+            return Err(Error::PricingMismatch);
+        };
+        if shares == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        if market.status != MarketStatus::Open {
+            return Err(Error::MarketNotOpen);
+        }
+        if current_block >= market.betting_closes_at {
+            return Err(Error::BettingClosed);
+        }
+        if option_index as usize >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+        Ok(liquidity)
+    }
+
+    /// Request market resolution (anyone can call after deadline)
+    ///
+    /// Submits an agent request to every resolver oracle through `ext` and
+    /// records the returned receipts, so callbacks for this market are only
+    /// accepted with one of the receipts' request IDs. A market already
+    /// pending resolution can be re-requested once its last round has failed
+    /// for good or passed its TTL, while it has rounds left and has not been
+    /// escalated; after that only the admin fallback can settle it.
+    pub fn request_resolution(
+        &mut self,
+        market_id: MarketId,
+        current_block: BlockNumber,
+        ext: &mut impl ChainExtension,
+    ) -> Result<Vec<AgentRunReceipt>, Error> {
+        // Find market
+        let mut market = self.load_market(market_id)?;
+
+        // Check deadline passed
+        if current_block < market.resolution_deadline {
+            return Err(Error::DeadlineNotReached);
+        }
+
+        let timed_out = match market.status {
+            MarketStatus::Open => false,
+            MarketStatus::PendingResolution => match self.load_pending_resolution(market_id) {
+                Some(pending) if current_block >= pending.expires_at => true,
+                Some(_) => return Err(Error::ResolutionInProgress),
+                None => false,
+            },
+            _ => return Err(Error::MarketNotOpen),
+        };
+        if market.escalated {
+            return Err(Error::ResolutionEscalated);
+        }
+        if market.resolution_attempts >= self.config.max_resolution_attempts {
+            return Err(Error::ResolutionAttemptsExhausted);
+        }
+        if timed_out {
+            // The late callback will no longer be accepted
+            self.clear_pending_resolution(market_id);
+        }
+
+        let receipts = self.submit_resolution_request(&mut market, current_block, ext)?;
+        market.status = MarketStatus::PendingResolution;
+        self.save_market(&market);
+        Ok(receipts)
+    }
+
+    /// Send `market` to every resolver oracle and record the round as pending
+    ///
+    /// A resolver whose request cannot be submitted is left out of the round
+    /// and counts as a failed run; this only fails if no request was submitted.
+    /// Counts the round in `market.resolution_attempts`; the caller saves the market.
+    fn submit_resolution_request(
+        &mut self,
+        market: &mut Market,
+        current_block: BlockNumber,
+        ext: &mut impl ChainExtension,
+    ) -> Result<Vec<AgentRunReceipt>, Error> {
+        // Get resolver agents
+        if self.config.resolver_agents.is_empty() {
+            return Err(Error::ResolverNotConfigured);
+        }
+
+        // Build resolution request
+        let request_input = MarketResolutionRequest {
+            market_id: market.id,
+            question: market.question.clone(),
+            options: market.options.clone(),
+            resolution_criteria: market.resolution_criteria.clone(),
+            resolution_source: market.resolution_source.clone(),
+        };
+
+        // Submit one run per resolver via chain extension
+        let input = request_input.encode();
+        let mut runs = Vec::with_capacity(self.config.resolver_agents.len());
+        let mut first_error = None;
+        for &resolver in &self.config.resolver_agents {
+            let submitted = ext.request_agent_run(&ContractAgentRequest {
+                target_agent: resolver,
+                input: input.clone(),
+                ttl_blocks: RESOLUTION_TTL_BLOCKS,
+                callback: Some(CallbackSpec {
+                    selector: selectors::ON_RESOLUTION_COMPLETE,
+                    gas_limit: 1_000_000_000, // 1B gas for settlement
+                }),
+            });
+            match submitted {
+                Ok(receipt) => runs.push(ResolverRun {
+                    resolver,
+                    receipt,
+                    status: RunStatus::Outstanding,
+                }),
+                Err(err) => {
+                    first_error.get_or_insert(Error::from(err));
+                }
+            }
+        }
+        // Runs already queued are paid for, so keep them unless none were
+        if runs.is_empty() {
+            return Err(first_error.unwrap_or(Error::ResolverNotConfigured));
+        }
+        let unsubmitted = (self.config.resolver_agents.len() - runs.len()) as u8;
+
+        // Remember which requests resolve this market
+        for run in &runs {
+            self.storage.set(&keys::resolution_request(run.receipt.request_id), &market.id);
+            self.emit(Event::ResolutionRequested {
+                market_id: market.id,
+                resolver: run.resolver,
+                request_id: run.receipt.request_id,
+            });
+        }
+        let pending = PendingResolution {
+            runs,
+            unsubmitted,
+            quorum: self.config.resolver_quorum,
+            min_confidence_pct: market.min_confidence_pct.unwrap_or(self.config.min_confidence_pct),
+            low_confidence_action: self.config.low_confidence_action,
+            expires_at: current_block.saturating_add(RESOLUTION_TTL_BLOCKS as BlockNumber),
+        };
+        self.storage.set(&keys::pending_resolution(market.id), &pending);
+        market.resolution_attempts = market.resolution_attempts.saturating_add(1);
+
+        Ok(pending.receipts())
+    }
+
+    fn load_pending_resolution(&self, market_id: MarketId) -> Option<PendingResolution> {
+        self.storage.get(&keys::pending_resolution(market_id))
+    }
+
+    /// Forget the outstanding round for a market
+    fn clear_pending_resolution(&mut self, market_id: MarketId) {
+        if let Some(pending) = self.load_pending_resolution(market_id) {
+            for run in &pending.runs {
+                self.storage.clear(&keys::resolution_request(run.receipt.request_id));
+            }
+            self.storage.clear(&keys::pending_resolution(market_id));
+        }
+    }
+
+    /// Handle resolution callback from oracle agent
+    ///
+    /// Only accepted from the runtime's agent-callback origin, for a request ID
+    /// recorded when resolution was requested, and at most once per request ID.
+    /// Each callback records one resolver's report; the market settles once
+    /// `quorum` runs report the same outcome. A run that failed, whose output
+    /// is not a valid resolution for the market, or whose confidence is below
+    /// the threshold in force when the round was requested counts as no report.
+    /// When quorum can no longer be reached, a round with low-confidence answers
+    /// is handled by the [`LowConfidenceAction`] of that time; otherwise a round with failed runs
+    /// is re-requested through `ext` until the market has used
+    /// `max_resolution_attempts` rounds, and one where the resolvers simply
+    /// disagreed is escalated to the admin. The callback itself still succeeds.
+    ///
+    /// The outcome is proposed and becomes final once the dispute window
+    /// closes. For a disputed market this is the second round, which settles
+    /// the dispute if it agrees with the proposal and escalates it otherwise.
+    pub fn on_resolution_complete(
+        &mut self,
+        caller: AccountId,
+        callback_payload: AgentCallbackPayload,
+        current_block: BlockNumber,
+        ext: &mut impl ChainExtension,
+    ) -> Result<(), Error> {
+        // Only the runtime may deliver agent callbacks
+        if self.config.agent_callback_origin != Some(caller) {
+            return Err(Error::NotAuthorized);
+        }
+
+        // Reject replays of a consumed request
+        let request_id = callback_payload.request_id;
+        if self.storage.contains(&keys::consumed_request(request_id)) {
+            return Err(Error::RequestAlreadyProcessed);
+        }
+
+        // Find the market this request was sent for
+        let market_id: MarketId = self.storage.get(&keys::resolution_request(request_id))
+            .ok_or(Error::RequestIdMismatch)?;
+        let mut market = self.load_market(market_id)?;
+
+        // Verify market is pending
+        if !matches!(market.status, MarketStatus::PendingResolution | MarketStatus::Disputed) {
+            return Err(Error::MarketNotPendingResolution);
+        }
+
+        // Verify this is one of the market's outstanding requests
+        let mut pending = self.load_pending_resolution(market_id).ok_or(Error::RequestIdMismatch)?;
+        let run = pending.runs.iter()
+            .position(|run| run.receipt.request_id == request_id && run.status == RunStatus::Outstanding)
+            .ok_or(Error::RequestIdMismatch)?;
+
+        // Mark the request consumed
+        self.storage.clear(&keys::resolution_request(request_id));
+        self.storage.set(&keys::consumed_request(request_id), &());
+
+        // Decode and validate resolution result
+        let result = Some(&callback_payload)
+            .filter(|payload| payload.success)
+            .and_then(|payload| ResolutionResult::decode(&mut &payload.output[..]).ok())
+            .filter(|result| result.market_id == market_id)
+            .filter(|result| result.void || (result.winning_option as usize) < market.options.len());
+
+        pending.runs[run].status = match &result {
+            Some(result) if result.confidence_pct < pending.min_confidence_pct => {
+                self.emit(Event::LowConfidenceResult {
+                    market_id,
+                    request_id,
+                    confidence_pct: result.confidence_pct,
+                });
+                RunStatus::LowConfidence
+            }
+            Some(result) => RunStatus::Reported(result.outcome()),
+            None => {
+                self.emit(Event::ResolutionFailed {
+                    market_id,
+                    request_id,
+                    attempt: market.resolution_attempts,
+                });
+                RunStatus::Failed
+            }
+        };
+
+        // Wait for the remaining runs while they can still decide the round
+        let outcome = pending.quorum_outcome();
+        if outcome.is_none() && pending.can_reach_quorum() {
+            self.storage.set(&keys::pending_resolution(market_id), &pending);
+            return Ok(());
+        }
+        self.clear_pending_resolution(market_id);
+        if let (Some(outcome), Some(result)) = (outcome, result) {
+            // Only a report can complete quorum, so this run agrees with it
+            if market.status == MarketStatus::Disputed {
+                market.disputed_resolution = market.resolution.take();
+            }
+            market.resolution = Some(Resolution {
+                outcome,
+                confidence_pct: result.confidence_pct,
+                evidence_summary: truncate_summary(result.evidence_summary),
+                resolver: pending.runs[run].resolver,
+                request_id,
+                run_id: callback_payload.run_id,
+                block: current_block,
+            });
+        }
+
+        if market.status == MarketStatus::Disputed {
+            let proposed = market.proposal.as_ref().map(|proposal| proposal.outcome);
+            match outcome {
+                Some(outcome) if Some(outcome) == proposed => self.settle_dispute(&mut market, outcome)?,
+                _ => {
+                    self.save_market(&market);
+                    self.emit(Event::DisputeEscalated { market_id });
+                }
+            }
+            return Ok(());
+        }
+
+        if let Some(outcome) = outcome {
+            return self.propose(&mut market, outcome, current_block);
+        }
+
+        let action = if pending.any(RunStatus::LowConfidence) {
+            pending.low_confidence_action
+        } else if pending.any(RunStatus::Failed) || pending.unsubmitted > 0 {
+            LowConfidenceAction::ReRequest
+        } else {
+            // Every resolver answered but no outcome reached quorum
+            LowConfidenceAction::Escalate
+        };
+        match action {
+            LowConfidenceAction::ReRequest => {
+                // Retry while attempts remain. If that is refused the market waits
+                // for a manual re-request; once none remain, for the admin fallback.
+                if market.resolution_attempts < self.config.max_resolution_attempts
+                    && self.submit_resolution_request(&mut market, current_block, ext).is_ok()
+                {
+                    self.save_market(&market);
+                }
+            }
+            LowConfidenceAction::Escalate => {
+                market.escalated = true;
+                self.save_market(&market);
+                self.emit(Event::ResolutionEscalated { market_id });
+            }
+            LowConfidenceAction::Void => self.propose(&mut market, Outcome::Void, current_block)?,
+        }
+        Ok(())
+    }
+
+    /// Propose `outcome` for `market` and save it, settling at once if there is no dispute window
+    fn propose(&mut self, market: &mut Market, outcome: Outcome, current_block: BlockNumber) -> Result<(), Error> {
+        if self.config.dispute_window_blocks == 0 {
+            return self.settle(market, outcome);
+        }
+
+        let dispute_ends_at = current_block.saturating_add(self.config.dispute_window_blocks);
+        market.status = MarketStatus::Proposed;
+        market.proposal = Some(Proposal {
+            outcome,
+            dispute_ends_at,
+            dispute: None,
+        });
+        self.save_market(market);
+
+        self.emit(Event::ResolutionProposed {
+            market_id: market.id,
+            outcome,
+            dispute_ends_at,
+        });
+        Ok(())
+    }
+
+    /// Settle `market` with `outcome` and save it
+    fn settle(&mut self, market: &mut Market, outcome: Outcome) -> Result<(), Error> {
+        match outcome {
+            Outcome::Winner(winning_option) => self.apply_resolution(market, winning_option)?,
+            Outcome::Void => {
+                // Ambiguous question or cancelled event: stakes are refunded
+                market.status = MarketStatus::Voided;
+                self.emit(Event::MarketVoided { market_id: market.id });
+                self.return_liquidity(market)?;
+                self.save_market(market);
+            }
+        }
+        Ok(())
+    }
+
+    /// Settle a disputed `market` with `outcome`, returning the bond if the
+    /// outcome differs from the proposal and slashing it to the treasury otherwise
+    fn settle_dispute(&mut self, market: &mut Market, outcome: Outcome) -> Result<(), Error> {
+        self.clear_pending_resolution(market.id);
+        if let Some(proposal) = market.proposal.clone() {
+            if let Some(dispute) = proposal.dispute {
+                let upheld = outcome != proposal.outcome;
+                if upheld {
+                    self.pay_out(market, dispute.disputer, dispute.bond)?;
+                } else {
+                    self.release_escrow(market, dispute.bond)?;
+                    self.accrue_protocol_fees(dispute.bond)?;
+                }
+                self.emit(Event::DisputeSettled {
+                    market_id: market.id,
+                    account: dispute.disputer,
+                    upheld,
+                });
+            }
+        }
+        self.settle(market, outcome)
+    }
+
+    /// Dispute a market's proposed outcome (bettors only)
+    ///
+    /// `transferred` is the value sent with the call and must equal the
+    /// configured dispute bond. A second resolver run is requested through
+    /// `ext`; if it cannot be submitted the dispute goes straight to the admin.
+    pub fn dispute_resolution(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        transferred: Balance,
+        current_block: BlockNumber,
+        ext: &mut impl ChainExtension,
+    ) -> Result<(), Error> {
+        let mut market = self.load_market(market_id)?;
+        let mut proposal = match (market.status, market.proposal.clone()) {
+            (MarketStatus::Proposed, Some(proposal)) => proposal,
+            (MarketStatus::Disputed, _) => return Err(Error::AlreadyDisputed),
+            _ => return Err(Error::MarketNotProposed),
+        };
+        if current_block >= proposal.dispute_ends_at {
+            return Err(Error::DisputeWindowClosed);
+        }
+        if self.load_position(market_id, &caller).is_none_or(|position| position.is_empty()) {
+            return Err(Error::NoPosition);
+        }
+        let bond = self.config.dispute_bond;
+        if transferred != bond {
+            return Err(Error::StakeMismatch);
+        }
+
+        proposal.dispute = Some(Dispute { disputer: caller, bond });
+        market.proposal = Some(proposal);
+        market.status = MarketStatus::Disputed;
+        self.escrow(&mut market, bond)?;
+        self.emit(Event::ResolutionDisputed {
+            market_id,
+            account: caller,
+            bond,
+        });
+
+        if self.submit_resolution_request(&mut market, current_block, ext).is_err() {
+            self.emit(Event::DisputeEscalated { market_id });
+        }
+        self.save_market(&market);
+        Ok(())
+    }
+
+    /// Make a proposed outcome final once its dispute window has closed (anyone can call)
+    pub fn finalize_resolution(&mut self, market_id: MarketId, current_block: BlockNumber) -> Result<(), Error> {
+        let mut market = self.load_market(market_id)?;
+        let proposal = match (market.status, market.proposal.clone()) {
+            (MarketStatus::Proposed, Some(proposal)) => proposal,
+            _ => return Err(Error::MarketNotProposed),
+        };
+        if current_block < proposal.dispute_ends_at {
+            return Err(Error::DisputeWindowOpen);
+        }
+
+        self.settle(&mut market, proposal.outcome)
+    }
+
+    /// Settle a disputed market by hand (arbitrator)
+    ///
+    /// Overrides a second resolver run that is still outstanding.
+    pub fn arbitrate_dispute(&mut self, caller: AccountId, market_id: MarketId, outcome: Outcome) -> Result<(), Error> {
+        self.check_role(caller, Role::Arbitrator)?;
+        let mut market = self.load_market(market_id)?;
+        if market.status != MarketStatus::Disputed {
+            return Err(Error::MarketNotDisputed);
+        }
+        if let Outcome::Winner(winning_option) = outcome {
+            if winning_option as usize >= market.options.len() {
+                return Err(Error::InvalidOptionIndex);
+            }
+        }
+
+        self.settle_dispute(&mut market, outcome)
+    }
+
+    /// Mark `market` resolved in favour of `winning_option` and save it
+    fn apply_resolution(&mut self, market: &mut Market, winning_option: OptionIndex) -> Result<(), Error> {
+        market.status = MarketStatus::Resolved;
+        market.winning_option = Some(winning_option);
+        if market.pricing == Pricing::Parimutuel && market.shares_per_option[winning_option as usize] == 0 {
+            market.no_winner_policy = Some(self.config.no_winner_policy);
+        }
+
+        self.emit(Event::MarketResolved {
+            market_id: market.id,
+            winning_option,
+        });
+        self.accrue_fees(market)?;
+        self.return_liquidity(market)?;
+        self.save_market(market);
+        Ok(())
+    }
+
+    /// Resolve a market whose oracle runs failed or timed out (arbitrator)
+    pub fn admin_resolve(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        winning_option: OptionIndex,
+        current_block: BlockNumber,
+    ) -> Result<(), Error> {
+        self.check_role(caller, Role::Arbitrator)?;
+        let mut market = self.load_market(market_id)?;
+        self.check_fallback_allowed(&market, current_block)?;
+        if winning_option as usize >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        self.clear_pending_resolution(market_id);
+        self.apply_resolution(&mut market, winning_option)
+    }
+
+    /// Void a market (arbitrator)
+    ///
+    /// Open markets can be voided at any time, e.g. when the event is
+    /// cancelled; markets pending resolution only once their oracle runs
+    /// failed or timed out. Bettors reclaim their stakes with `claim_refund`.
+    pub fn void_market(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        current_block: BlockNumber,
+    ) -> Result<(), Error> {
+        self.check_role(caller, Role::Arbitrator)?;
+        let mut market = self.load_market(market_id)?;
+        if market.status != MarketStatus::Open {
+            self.check_fallback_allowed(&market, current_block)?;
+        }
+
+        self.clear_pending_resolution(market_id);
+        self.settle(&mut market, Outcome::Void)
+    }
+
+    /// Cancel a market before it is resolved
+    ///
+    /// The arbitrator can cancel a market that is open, or pending resolution
+    /// with no resolver run still inside its TTL; its creator only while it is
+    /// open and nobody has bet. Bettors reclaim their stakes with `claim_refund`.
+    pub fn cancel_market(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        current_block: BlockNumber,
+    ) -> Result<(), Error> {
+        let mut market = self.load_market(market_id)?;
+        if self.has_role(caller, Role::Arbitrator) {
+            match market.status {
+                MarketStatus::Open => {}
+                MarketStatus::PendingResolution => self.check_fallback_allowed(&market, current_block)?,
+                _ => return Err(Error::MarketNotOpen),
+            }
+        } else if caller == market.creator {
+            if market.status != MarketStatus::Open {
+                return Err(Error::MarketNotOpen);
+            }
+            if market.total_pool()? != 0 {
+                return Err(Error::PoolNotEmpty);
+            }
+        } else {
+            return Err(Error::NotAuthorized);
+        }
+
+        self.clear_pending_resolution(market_id);
+        market.status = MarketStatus::Cancelled;
+        self.emit(Event::MarketCancelled {
+            market_id,
+            cancelled_by: caller,
+        });
+        self.return_liquidity(&mut market)?;
+        self.save_market(&market);
+        Ok(())
+    }
+
+    /// Check that the admin may settle `market` by hand: it is pending
+    /// resolution with no resolver run still inside its TTL
+    fn check_fallback_allowed(&self, market: &Market, current_block: BlockNumber) -> Result<(), Error> {
+        if market.status != MarketStatus::PendingResolution {
+            return Err(Error::MarketNotPendingResolution);
+        }
+        if let Some(pending) = self.load_pending_resolution(market.id) {
+            if current_block < pending.expires_at {
+                return Err(Error::ResolutionInProgress);
+            }
+        }
+        Ok(())
+    }
+
+    /// Claim winnings from a resolved market
+    pub fn claim_winnings(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+    ) -> Result<Balance, Error> {
+        // Find market
+        let mut market = self.load_market(market_id)?;
+
+        // Check resolved
+        if market.status != MarketStatus::Resolved {
+            return Err(Error::MarketNotResolved);
+        }
+
+        // Find user position
+        let position = self.load_position(market_id, &caller)
+            .ok_or(Error::NoPosition)?;
+
+        // Winner gets proportional share of total pool
+        let payout = market.winnings(&position)?;
+
+        // Remove position (claimed) and pay out of escrow
+        self.storage.clear(&keys::position(market_id, &caller));
+        self.pay_out(&mut market, caller, payout)?;
+        self.save_market(&market);
+
+        self.emit(Event::WinningsClaimed {
+            market_id,
+            account: caller,
+            payout,
+        });
+
+        Ok(payout)
+    }
+
+    /// Reclaim the full stake from a voided or cancelled market, or from a
+    /// resolved market nobody won whose pool is refunded
+    pub fn claim_refund(&mut self, caller: AccountId, market_id: MarketId) -> Result<Balance, Error> {
+        let mut market = self.load_market(market_id)?;
+        if !market.is_refunding() {
+            return Err(Error::MarketNotVoided);
+        }
+
+        let position = self.load_position(market_id, &caller)
+            .ok_or(Error::NoPosition)?;
+        let amount = market.refund(&position)?;
+
+        self.storage.clear(&keys::position(market_id, &caller));
+        self.pay_out(&mut market, caller, amount)?;
+        self.save_market(&market);
+
+        self.emit(Event::RefundClaimed {
+            market_id,
+            account: caller,
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// Withdraw the creator fees accrued to the caller across their markets
+    pub fn withdraw_creator_fees(&mut self, caller: AccountId) -> Result<Balance, Error> {
+        let amount = self.get_creator_fees(caller);
+        if amount == 0 {
+            return Err(Error::NoFeesAccrued);
+        }
+
+        self.storage.clear(&keys::creator_fees(&caller));
+        self.transfers.push(Transfer { to: caller, amount });
+        self.emit(Event::FeesWithdrawn {
+            recipient: caller,
+            amount,
+        });
+        Ok(amount)
+    }
+
+    /// Send the pool of a market nobody won to the treasury (anyone can call)
+    ///
+    /// Only for markets resolved under [`NoWinnerPolicy::Treasury`].
+    pub fn sweep_to_treasury(&mut self, market_id: MarketId) -> Result<Balance, Error> {
+        let mut market = self.load_market(market_id)?;
+        if market.status != MarketStatus::Resolved || market.no_winner_policy != Some(NoWinnerPolicy::Treasury) {
+            return Err(Error::NotTreasuryPool);
+        }
+        let treasury = self.config.treasury.ok_or(Error::TreasuryNotConfigured)?;
+        let amount = market.escrowed;
+        if amount == 0 {
+            return Err(Error::NotTreasuryPool);
+        }
+
+        self.pay_out(&mut market, treasury, amount)?;
+        self.save_market(&market);
+
+        self.emit(Event::PoolSweptToTreasury {
+            market_id,
+            treasury,
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// Transfer `amount` shares of an option from the caller's position to `to`
+    ///
+    /// Works in every market state until the position is claimed, so a
+    /// winning position can be handed over before `claim_winnings`.
+    pub fn transfer_position(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        to: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    ) -> Result<(), Error> {
+        self.move_shares(market_id, caller, to, option_index, amount)
+    }
+
+    /// Let `spender` transfer up to `amount` shares of an option out of the caller's position
+    ///
+    /// Replaces any previous allowance; approving 0 revokes it. The caller
+    /// cannot approve itself.
+    pub fn approve(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        spender: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    ) -> Result<(), Error> {
+        if spender == caller {
+            return Err(Error::InvalidRecipient);
+        }
+        let market = self.load_market(market_id)?;
+        if option_index as usize >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        self.set_allowance(market_id, &caller, &spender, option_index, amount);
+        self.emit(Event::Approval {
+            market_id,
+            owner: caller,
+            spender,
+            option_index,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Transfer `amount` shares of an option from `from`'s position to `to`,
+    /// spending the allowance `from` approved for the caller
+    pub fn transfer_position_from(
+        &mut self,
+        caller: AccountId,
+        market_id: MarketId,
+        from: AccountId,
+        to: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    ) -> Result<(), Error> {
+        let allowance = self.get_allowance(market_id, from, caller, option_index);
+        let remaining = allowance.checked_sub(amount).ok_or(Error::InsufficientAllowance)?;
+        self.move_shares(market_id, from, to, option_index, amount)?;
+        self.set_allowance(market_id, &from, &caller, option_index, remaining);
+        Ok(())
+    }
+
+    /// Move `amount` shares of an option between two positions
+    ///
+    /// While a parimutuel market is open the recipient's stake is held to the
+    /// per-account cap, so stakes from several accounts cannot be pooled past it.
+    fn move_shares(
+        &mut self,
+        market_id: MarketId,
+        from: AccountId,
+        to: AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    ) -> Result<(), Error> {
+        if amount == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        if from == to {
+            return Err(Error::InvalidRecipient);
+        }
+        let market = self.load_market(market_id)?;
+        let idx = option_index as usize;
+        if idx >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        let mut sender = self.load_position(market_id, &from).ok_or(Error::NoPosition)?;
+        sender.shares[idx] = sender.shares[idx].checked_sub(amount).ok_or(Error::InsufficientShares)?;
+        let mut recipient = self.load_position(market_id, &to)
+            .unwrap_or_else(|| Position::new(market.options.len()));
+        recipient.shares[idx] = recipient.shares[idx].checked_add(amount).ok_or(Error::Overflow)?;
+        if market.pricing == Pricing::Parimutuel && market.status == MarketStatus::Open {
+            market.bet_limits.unwrap_or(self.config.bet_limits)
+                .check_position(recipient.total_shares()?)?;
+        }
+
+        self.save_position(market_id, &from, &sender);
+        self.save_position(market_id, &to, &recipient);
+        self.emit(Event::PositionTransferred {
+            market_id,
+            from,
+            to,
+            option_index,
+            amount,
+        });
+        Ok(())
+    }
+
+    fn set_allowance(
+        &mut self,
+        market_id: MarketId,
+        owner: &AccountId,
+        spender: &AccountId,
+        option_index: OptionIndex,
+        amount: Balance,
+    ) {
+        let key = keys::allowance(market_id, owner, spender, option_index);
+        if amount == 0 {
+            self.storage.clear(&key);
+        } else {
+            self.storage.set(&key, &amount);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // View Functions
+    // ------------------------------------------------------------------------
+
+    /// Get market details
+    pub fn get_market(&self, market_id: MarketId) -> Option<Market> {
+        self.load_market(market_id).ok()
+    }
+
+    /// Get user position in a market
+    pub fn get_position(&self, market_id: MarketId, account: AccountId) -> Position {
+        self.load_position(market_id, &account).unwrap_or_default()
+    }
+
+    /// Get what `account` can claim from a market, and through which call
+    pub fn get_claimable(&self, market_id: MarketId, account: AccountId) -> Claimable {
+        let Ok(market) = self.load_market(market_id) else {
+            return Claimable::Nothing;
+        };
+        if !matches!(market.status, MarketStatus::Resolved | MarketStatus::Voided | MarketStatus::Cancelled) {
+            return Claimable::NotSettled;
+        }
+        if market.no_winner_policy == Some(NoWinnerPolicy::Treasury) {
+            return Claimable::PoolToTreasury;
+        }
+        let Some(position) = self.load_position(market_id, &account) else {
+            return Claimable::Nothing;
+        };
+        if market.is_refunding() {
+            return market.refund(&position).map_or(Claimable::Nothing, Claimable::Refund);
+        }
+        market.winnings(&position).map_or(Claimable::Nothing, Claimable::Winnings)
+    }
+
+    /// Get the oracle reports a market's outcome was proposed or settled on, oldest first
+    ///
+    /// Holds at most two: the disputed report and the second round's, if it reached quorum.
+    pub fn get_resolution(&self, market_id: MarketId) -> Vec<Resolution> {
+        self.load_market(market_id)
+            .map(|market| market.disputed_resolution.into_iter().chain(market.resolution).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get a market's outstanding resolver run
+    pub fn get_pending_resolution(&self, market_id: MarketId) -> Option<PendingResolution> {
+        self.load_pending_resolution(market_id)
+    }
+
+    /// Get the total funds held in escrow across all markets
+    pub fn get_total_escrowed(&self) -> Balance {
+        self.total_escrowed()
+    }
+
+    /// Get the protocol fees accrued and not yet withdrawn to the treasury
+    pub fn get_accrued_fees(&self) -> Balance {
+        self.storage.get(&keys::accrued_fees()).unwrap_or_default()
+    }
+
+    /// Get the creator fees accrued to `creator` and not yet withdrawn
+    pub fn get_creator_fees(&self, creator: AccountId) -> Balance {
+        self.storage.get(&keys::creator_fees(&creator)).unwrap_or_default()
+    }
+
+    /// Get the shares of an option `spender` may still transfer out of `owner`'s position
+    pub fn get_allowance(
+        &self,
+        market_id: MarketId,
+        owner: AccountId,
+        spender: AccountId,
+        option_index: OptionIndex,
+    ) -> Balance {
+        self.storage.get(&keys::allowance(market_id, &owner, &spender, option_index)).unwrap_or_default()
+    }
+
+    /// Get the admin, any proposed admin, and the holder of each role
+    pub fn get_roles(&self) -> Roles {
+        Roles {
+            admin: self.config.admin,
+            pending_admin: self.config.pending_admin,
+            config_manager: self.config.config_manager,
+            fee_manager: self.config.fee_manager,
+            pauser: self.config.pauser,
+            arbitrator: self.config.arbitrator,
+        }
+    }
+
+    /// Get contract configuration
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Get implied odds for each option (based on current shares)
+    /// Returns percentages that sum to 100; LMSR markets return their prices
+    pub fn get_implied_odds(&self, market_id: MarketId) -> Option<Vec<u8>> {
+        let market = self.get_market(market_id)?;
+        if let Pricing::Lmsr { liquidity } = market.pricing {
+            return Some(
+                lmsr::prices(&market.shares_per_option, liquidity)
+                    .into_iter()
+                    .map(|price| math::mul_div(price, 100, lmsr::ONE).unwrap_or_default() as u8)
+                    .collect(),
+            );
+        }
+        let total = market.total_pool().ok()?;
+        
+        if total == 0 {
+            // Equal odds when no bets
+            let equal = 100 / market.options.len() as u8;
+            return Some(vec![equal; market.options.len()]);
+        }
+
+        Some(
+            market.shares_per_option
+                .iter()
+                .map(|&shares| math::mul_div(shares, 100, total).unwrap_or_default() as u8)
+                .collect()
+        )
+    }
+
+    /// Get what buying or selling `shares` of an option in an LMSR market would cost now
+    pub fn quote(&self, market_id: MarketId, option_index: OptionIndex, shares: Balance) -> Result<Quote, Error> {
+        let market = self.load_market(market_id)?;
+        let Pricing::Lmsr { liquidity } = market.pricing else {
+            return Err(Error::PricingMismatch);
+        };
+        let idx = option_index as usize;
+        if idx >= market.options.len() {
+            return Err(Error::InvalidOptionIndex);
+        }
+
+        Ok(Quote {
+            buy_cost: lmsr::buy_cost(&market.shares_per_option, liquidity, idx, shares)?,
+            sell_proceeds: lmsr::sell_proceeds(&market.shares_per_option, liquidity, idx, shares).ok(),
+        })
+    }
+}
+
+// ============================================================================
+// Entry Point Dispatch (WASM)
+// ============================================================================
+
+/// Host-provided context for a single contract call
+#[derive(Clone, Copy, Debug)]
+pub struct CallContext {
+    /// Account that invoked the contract
+    pub caller: AccountId,
+    /// Current block number
+    pub block_number: BlockNumber,
+    /// Value transferred along with the call
+    pub value_transferred: Balance,
+}
+
+/// Result of dispatching a call, ready to be handed back to the host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DispatchOutput {
+    /// Whether the host should roll back state changes made by the call
+    pub revert: bool,
+    /// SCALE-encoded return value
+    pub data: Vec<u8>,
+}
+
+impl DispatchOutput {
+    /// Encode a method result, reverting on `Err`
+    fn from_result<T: Encode>(result: Result<T, Error>) -> Self {
+        Self {
+            revert: result.is_err(),
+            data: result.encode(),
+        }
+    }
+
+    /// Encode a view return value (never reverts)
+    fn from_view<T: Encode>(value: T) -> Self {
+        Self {
+            revert: false,
+            data: value.encode(),
+        }
+    }
+}
+
+/// Split call data into its 4-byte selector and argument bytes
+fn split_selector(input: &[u8]) -> Result<([u8; 4], &[u8]), Error> {
+    if input.len() < 4 {
+        return Err(Error::UnknownSelector);
+    }
+    let (selector, args) = input.split_at(4);
+    Ok(([selector[0], selector[1], selector[2], selector[3]], args))
+}
+
+/// SCALE-decode the arguments of a call
+fn decode_args<T: Decode>(mut args: &[u8]) -> Result<T, Error> {
+    T::decode(&mut args).map_err(|_| Error::InvalidArguments)
+}
+
+impl<S: Storage> PredictionMarket<S> {
+    /// Run the constructor from raw deploy input
+    ///
+    /// Expects `CONSTRUCTOR ++ admin.encode()`.
+    pub fn instantiate(storage: S, input: &[u8]) -> Result<Self, Error> {
+        let (selector, args) = split_selector(input)?;
+        if selector != selectors::CONSTRUCTOR {
+            return Err(Error::UnknownSelector);
+        }
+        let admin: AccountId = decode_args(args)?;
+        Ok(Self::init(storage, admin))
+    }
+
+    /// Route raw call data to the matching contract method
+    ///
+    /// Call data is a 4-byte selector followed by the SCALE-encoded arguments.
+    /// Mutating methods return an encoded `Result`; views return their value directly.
+    /// Events and transfers made by a reverted call are discarded. Only
+    /// `place_bet`, `dispute_resolution`, `create_lmsr_market` and
+    /// `buy_shares` accept value; other methods revert with `NotPayable`.
+    ///
+    /// Agent requests are submitted through `ext`.
+    pub fn dispatch(&mut self, ctx: &CallContext, input: &[u8], ext: &mut impl ChainExtension) -> DispatchOutput {
+        let output = self.route(ctx, input, ext);
+        if output.revert {
+            // State changes are rolled back by the host, so are their effects
+            self.events.clear();
+            self.transfers.clear();
+        }
+        output
+    }
+
+    fn route(&mut self, ctx: &CallContext, input: &[u8], ext: &mut impl ChainExtension) -> DispatchOutput {
+        let (selector, args) = match split_selector(input) {
+            Ok(split) => split,
+            Err(e) => return DispatchOutput::from_result::<()>(Err(e)),
+        };
+
+        let payable = matches!(
+            selector,
+            selectors::PLACE_BET
+                | selectors::DISPUTE_RESOLUTION
+                | selectors::CREATE_LMSR_MARKET
+                | selectors::BUY_SHARES
+        );
+        if ctx.value_transferred != 0 && !payable {
+            return DispatchOutput::from_result::<()>(Err(Error::NotPayable));
+        }
+
+        match selector {
+            selectors::SET_MARKET_CREATOR => DispatchOutput::from_result(
+                decode_args(args).and_then(|agent_id| self.set_market_creator(ctx.caller, agent_id)),
+            ),
+            selectors::SET_RESOLVER_ORACLE => DispatchOutput::from_result(
+                decode_args(args).and_then(|agent_id| self.set_resolver_oracle(ctx.caller, agent_id)),
+            ),
+            selectors::SET_CALLBACK_ORIGIN => DispatchOutput::from_result(
+                decode_args(args).and_then(|origin| self.set_callback_origin(ctx.caller, origin)),
+            ),
+            selectors::SET_MAX_RESOLUTION_ATTEMPTS => DispatchOutput::from_result(
+                decode_args(args).and_then(|attempts| self.set_max_resolution_attempts(ctx.caller, attempts)),
+            ),
+            selectors::SET_TREASURY => DispatchOutput::from_result(
+                decode_args(args).and_then(|treasury| self.set_treasury(ctx.caller, treasury)),
+            ),
+            selectors::SET_NO_WINNER_POLICY => DispatchOutput::from_result(
+                decode_args(args).and_then(|policy| self.set_no_winner_policy(ctx.caller, policy)),
+            ),
+            selectors::SET_BETTING_CUTOFF => DispatchOutput::from_result(
+                decode_args(args).and_then(|blocks| self.set_betting_cutoff(ctx.caller, blocks)),
+            ),
+            selectors::SET_EXIT_PENALTY => DispatchOutput::from_result(
+                decode_args(args).and_then(|penalty_bps| self.set_exit_penalty(ctx.caller, penalty_bps)),
+            ),
+            selectors::SET_MIN_CONFIDENCE => DispatchOutput::from_result(
+                decode_args(args).and_then(|(pct, action)| self.set_min_confidence(ctx.caller, pct, action)),
+            ),
+            selectors::SET_MARKET_MIN_CONFIDENCE => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, pct)| self.set_market_min_confidence(ctx.caller, market_id, pct),
+            )),
+            selectors::SET_ROLE => DispatchOutput::from_result(
+                decode_args(args).and_then(|(role, account)| self.set_role(ctx.caller, role, account)),
+            ),
+            selectors::PROPOSE_ADMIN => DispatchOutput::from_result(
+                decode_args(args).and_then(|new_admin| self.propose_admin(ctx.caller, new_admin)),
+            ),
+            selectors::ACCEPT_ADMIN => DispatchOutput::from_result(self.accept_admin(ctx.caller)),
+            selectors::SET_PAUSED => DispatchOutput::from_result(
+                decode_args(args).and_then(|paused| self.set_paused(ctx.caller, paused)),
+            ),
+            selectors::SET_BET_LIMITS => DispatchOutput::from_result(
+                decode_args(args).and_then(|limits| self.set_bet_limits(ctx.caller, limits)),
+            ),
+            selectors::SET_MARKET_BET_LIMITS => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, limits)| self.set_market_bet_limits(ctx.caller, market_id, limits),
+            )),
+            selectors::SET_RESOLVERS => DispatchOutput::from_result(
+                decode_args(args).and_then(|(agents, quorum)| self.set_resolvers(ctx.caller, agents, quorum)),
+            ),
+            selectors::SET_DISPUTE_WINDOW => DispatchOutput::from_result(
+                decode_args(args).and_then(|(blocks, bond)| self.set_dispute_window(ctx.caller, blocks, bond)),
+            ),
+            selectors::SET_PROTOCOL_FEE => DispatchOutput::from_result(
+                decode_args(args).and_then(|fee_bps| self.set_protocol_fee(ctx.caller, fee_bps)),
+            ),
+            selectors::CREATE_MARKET => DispatchOutput::from_result(decode_args(args).and_then(
+                |(question, options, resolution_criteria, resolution_source, resolution_deadline, creator_fee_bps)| {
+                    self.create_market(
+                        ctx.caller,
+                        question,
+                        options,
+                        resolution_criteria,
+                        resolution_source,
+                        resolution_deadline,
+                        creator_fee_bps,
+                    )
+                },
+            )),
+            selectors::CREATE_LMSR_MARKET => DispatchOutput::from_result(decode_args(args).and_then(
+                |(question, options, resolution_criteria, resolution_source, resolution_deadline, liquidity)| {
+                    self.create_lmsr_market(
+                        ctx.caller,
+                        question,
+                        options,
+                        resolution_criteria,
+                        resolution_source,
+                        resolution_deadline,
+                        liquidity,
+                        ctx.value_transferred,
+                    )
+                },
+            )),
+            selectors::WITHDRAW_BET => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, option_index, amount)| {
+                    self.withdraw_bet(ctx.caller, market_id, option_index, amount, ctx.block_number)
+                },
+            )),
+            selectors::TRANSFER_POSITION => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, to, option_index, amount)| {
+                    self.transfer_position(ctx.caller, market_id, to, option_index, amount)
+                },
+            )),
+            selectors::APPROVE => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, spender, option_index, amount)| {
+                    self.approve(ctx.caller, market_id, spender, option_index, amount)
+                },
+            )),
+            selectors::TRANSFER_POSITION_FROM => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, from, to, option_index, amount)| {
+                    self.transfer_position_from(ctx.caller, market_id, from, to, option_index, amount)
+                },
+            )),
+            selectors::BUY_SHARES => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, option_index, shares)| {
+                    self.buy_shares(ctx.caller, market_id, option_index, shares, ctx.value_transferred, ctx.block_number)
+                },
+            )),
+            selectors::SELL_SHARES => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, option_index, shares, min_proceeds)| {
+                    self.sell_shares(ctx.caller, market_id, option_index, shares, min_proceeds, ctx.block_number)
+                },
+            )),
+            selectors::QUOTE => match decode_args(args) {
+                Ok((market_id, option_index, shares)) => DispatchOutput::from_view(self.quote(market_id, option_index, shares)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::PLACE_BET => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, option_index, amount)| {
+                    self.place_bet(ctx.caller, market_id, option_index, amount, ctx.value_transferred, ctx.block_number)
+                },
+            )),
+            selectors::REQUEST_RESOLUTION => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.request_resolution(market_id, ctx.block_number, ext)),
+            ),
+            selectors::ON_RESOLUTION_COMPLETE => DispatchOutput::from_result(
+                decode_args(args)
+                    .and_then(|payload| self.on_resolution_complete(ctx.caller, payload, ctx.block_number, ext)),
+            ),
+            selectors::CLAIM_WINNINGS => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.claim_winnings(ctx.caller, market_id)),
+            ),
+            selectors::ADMIN_RESOLVE => DispatchOutput::from_result(decode_args(args).and_then(
+                |(market_id, winning_option)| {
+                    self.admin_resolve(ctx.caller, market_id, winning_option, ctx.block_number)
+                },
+            )),
+            selectors::VOID_MARKET => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.void_market(ctx.caller, market_id, ctx.block_number)),
+            ),
+            selectors::CANCEL_MARKET => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.cancel_market(ctx.caller, market_id, ctx.block_number)),
+            ),
+            selectors::CLAIM_REFUND => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.claim_refund(ctx.caller, market_id)),
+            ),
+            selectors::SWEEP_TO_TREASURY => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.sweep_to_treasury(market_id)),
+            ),
+            selectors::DISPUTE_RESOLUTION => DispatchOutput::from_result(decode_args(args).and_then(|market_id| {
+                self.dispute_resolution(ctx.caller, market_id, ctx.value_transferred, ctx.block_number, ext)
+            })),
+            selectors::FINALIZE_RESOLUTION => DispatchOutput::from_result(
+                decode_args(args).and_then(|market_id| self.finalize_resolution(market_id, ctx.block_number)),
+            ),
+            selectors::ARBITRATE_DISPUTE => DispatchOutput::from_result(
+                decode_args(args).and_then(|(market_id, outcome)| self.arbitrate_dispute(ctx.caller, market_id, outcome)),
+            ),
+            selectors::WITHDRAW_FEES => DispatchOutput::from_result(self.withdraw_fees(ctx.caller)),
+            selectors::WITHDRAW_CREATOR_FEES => DispatchOutput::from_result(self.withdraw_creator_fees(ctx.caller)),
+            selectors::GET_ACCRUED_FEES => DispatchOutput::from_view(self.get_accrued_fees()),
+            selectors::GET_ROLES => DispatchOutput::from_view(self.get_roles()),
+            selectors::GET_ALLOWANCE => match decode_args(args) {
+                Ok((market_id, owner, spender, option_index)) => {
+                    DispatchOutput::from_view(self.get_allowance(market_id, owner, spender, option_index))
+                }
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_CREATOR_FEES => match decode_args(args) {
+                Ok(creator) => DispatchOutput::from_view(self.get_creator_fees(creator)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_CLAIMABLE => match decode_args(args) {
+                Ok((market_id, account)) => DispatchOutput::from_view(self.get_claimable(market_id, account)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_MARKET => match decode_args(args) {
+                Ok(market_id) => DispatchOutput::from_view(self.get_market(market_id)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_POSITION => match decode_args(args) {
+                Ok((market_id, account)) => DispatchOutput::from_view(self.get_position(market_id, account)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_IMPLIED_ODDS => match decode_args(args) {
+                Ok(market_id) => DispatchOutput::from_view(self.get_implied_odds(market_id)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_RESOLUTION => match decode_args(args) {
+                Ok(market_id) => DispatchOutput::from_view(self.get_resolution(market_id)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_PENDING_RESOLUTION => match decode_args(args) {
+                Ok(market_id) => DispatchOutput::from_view(self.get_pending_resolution(market_id)),
+                Err(e) => DispatchOutput::from_result::<()>(Err(e)),
+            },
+            selectors::GET_TOTAL_ESCROWED => DispatchOutput::from_view(self.get_total_escrowed()),
+            _ => DispatchOutput::from_result::<()>(Err(Error::UnknownSelector)),
+        }
+    }
+}
+
+/// Function selectors
+pub mod selectors {
+    pub const CONSTRUCTOR: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+    pub const SET_MARKET_CREATOR: [u8; 4] = [0x00, 0x00, 0x00, 0x02];
+    pub const SET_RESOLVER_ORACLE: [u8; 4] = [0x00, 0x00, 0x00, 0x03];
+    pub const SET_CALLBACK_ORIGIN: [u8; 4] = [0x00, 0x00, 0x00, 0x04];
+    pub const SET_MAX_RESOLUTION_ATTEMPTS: [u8; 4] = [0x00, 0x00, 0x00, 0x05];
+    pub const SET_TREASURY: [u8; 4] = [0x00, 0x00, 0x00, 0x06];
+    pub const SET_NO_WINNER_POLICY: [u8; 4] = [0x00, 0x00, 0x00, 0x07];
+    pub const SET_BETTING_CUTOFF: [u8; 4] = [0x00, 0x00, 0x00, 0x08];
+    pub const SET_PROTOCOL_FEE: [u8; 4] = [0x00, 0x00, 0x00, 0x09];
+    pub const SET_DISPUTE_WINDOW: [u8; 4] = [0x00, 0x00, 0x00, 0x0a];
+    pub const SET_RESOLVERS: [u8; 4] = [0x00, 0x00, 0x00, 0x0b];
+    pub const SET_MIN_CONFIDENCE: [u8; 4] = [0x00, 0x00, 0x00, 0x0c];
+    pub const SET_EXIT_PENALTY: [u8; 4] = [0x00, 0x00, 0x00, 0x0d];
+    pub const SET_BET_LIMITS: [u8; 4] = [0x00, 0x00, 0x00, 0x0e];
+    pub const SET_ROLE: [u8; 4] = [0x00, 0x00, 0x00, 0x0f];
+    pub const PROPOSE_ADMIN: [u8; 4] = [0x00, 0x00, 0x00, 0x10];
+    pub const ACCEPT_ADMIN: [u8; 4] = [0x00, 0x00, 0x00, 0x11];
+    pub const SET_PAUSED: [u8; 4] = [0x00, 0x00, 0x00, 0x12];
+    pub const CREATE_MARKET: [u8; 4] = [0x01, 0x00, 0x00, 0x01];
+    pub const PLACE_BET: [u8; 4] = [0x02, 0x00, 0x00, 0x01];
+    pub const REQUEST_RESOLUTION: [u8; 4] = [0x03, 0x00, 0x00, 0x01];
+    pub const ON_RESOLUTION_COMPLETE: [u8; 4] = [0x04, 0x00, 0x00, 0x01];
+    pub const CLAIM_WINNINGS: [u8; 4] = [0x05, 0x00, 0x00, 0x01];
+    pub const GET_MARKET: [u8; 4] = [0x06, 0x00, 0x00, 0x01];
+    pub const GET_POSITION: [u8; 4] = [0x07, 0x00, 0x00, 0x01];
+    pub const GET_IMPLIED_ODDS: [u8; 4] = [0x08, 0x00, 0x00, 0x01];
+    pub const GET_PENDING_RESOLUTION: [u8; 4] = [0x09, 0x00, 0x00, 0x01];
+    pub const GET_TOTAL_ESCROWED: [u8; 4] = [0x0a, 0x00, 0x00, 0x01];
+    pub const ADMIN_RESOLVE: [u8; 4] = [0x0b, 0x00, 0x00, 0x01];
+    pub const VOID_MARKET: [u8; 4] = [0x0c, 0x00, 0x00, 0x01];
+    pub const CLAIM_REFUND: [u8; 4] = [0x0d, 0x00, 0x00, 0x01];
+    pub const SWEEP_TO_TREASURY: [u8; 4] = [0x0e, 0x00, 0x00, 0x01];
+    pub const GET_CLAIMABLE: [u8; 4] = [0x0f, 0x00, 0x00, 0x01];
+    pub const WITHDRAW_FEES: [u8; 4] = [0x10, 0x00, 0x00, 0x01];
+    pub const WITHDRAW_CREATOR_FEES: [u8; 4] = [0x11, 0x00, 0x00, 0x01];
+    pub const GET_ACCRUED_FEES: [u8; 4] = [0x12, 0x00, 0x00, 0x01];
+    pub const GET_CREATOR_FEES: [u8; 4] = [0x13, 0x00, 0x00, 0x01];
+    pub const DISPUTE_RESOLUTION: [u8; 4] = [0x14, 0x00, 0x00, 0x01];
+    pub const FINALIZE_RESOLUTION: [u8; 4] = [0x15, 0x00, 0x00, 0x01];
+    pub const ARBITRATE_DISPUTE: [u8; 4] = [0x16, 0x00, 0x00, 0x01];
+    pub const SET_MARKET_MIN_CONFIDENCE: [u8; 4] = [0x17, 0x00, 0x00, 0x01];
+    pub const GET_RESOLUTION: [u8; 4] = [0x18, 0x00, 0x00, 0x01];
+    pub const CANCEL_MARKET: [u8; 4] = [0x19, 0x00, 0x00, 0x01];
+    pub const CREATE_LMSR_MARKET: [u8; 4] = [0x1a, 0x00, 0x00, 0x01];
+    pub const BUY_SHARES: [u8; 4] = [0x1b, 0x00, 0x00, 0x01];
+    pub const SELL_SHARES: [u8; 4] = [0x1c, 0x00, 0x00, 0x01];
+    pub const QUOTE: [u8; 4] = [0x1d, 0x00, 0x00, 0x01];
+    pub const WITHDRAW_BET: [u8; 4] = [0x1e, 0x00, 0x00, 0x01];
+    pub const TRANSFER_POSITION: [u8; 4] = [0x1f, 0x00, 0x00, 0x01];
+    pub const APPROVE: [u8; 4] = [0x20, 0x00, 0x00, 0x01];
+    pub const TRANSFER_POSITION_FROM: [u8; 4] = [0x21, 0x00, 0x00, 0x01];
+    pub const GET_ALLOWANCE: [u8; 4] = [0x22, 0x00, 0x00, 0x01];
+    pub const SET_MARKET_BET_LIMITS: [u8; 4] = [0x23, 0x00, 0x00, 0x01];
+    pub const GET_ROLES: [u8; 4] = [0x24, 0x00, 0x00, 0x01];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_ext::{ChainExtError, MockChainExtension};
+    use proptest::prelude::*;
+
+    fn alice() -> AccountId {
+        [1u8; 32]
+    }
+
+    fn bob() -> AccountId {
+        [2u8; 32]
+    }
+
+    fn charlie() -> AccountId {
+        [3u8; 32]
+    }
+
+    fn market_creator() -> AccountId {
+        [10u8; 32]
+    }
+
+    fn resolver_oracle() -> AccountId {
+        [11u8; 32]
+    }
+
+    fn callback_origin() -> AccountId {
+        [12u8; 32]
+    }
+
+    fn receipt(request_id: u64) -> AgentRunReceipt {
+        AgentRunReceipt {
+            request_id,
+            estimated_start_block: 0,
+        }
+    }
+
+    /// Chain extension that queues runs starting at `request_id`
+    fn queued(request_id: u64) -> MockChainExtension {
+        MockChainExtension::queueing_from(request_id)
+    }
+
+    fn failed_callback(request_id: u64) -> AgentCallbackPayload {
+        AgentCallbackPayload {
+            request_id,
+            run_id: request_id,
+            success: false,
+            output: Vec::new(),
+        }
+    }
+
+    fn resolution_callback(request_id: u64, market_id: MarketId, winning_option: OptionIndex) -> AgentCallbackPayload {
+        scored_callback(request_id, market_id, winning_option, 90)
+    }
+
+    fn scored_callback(
+        request_id: u64,
+        market_id: MarketId,
+        winning_option: OptionIndex,
+        confidence_pct: u8,
+    ) -> AgentCallbackPayload {
+        AgentCallbackPayload {
+            request_id,
+            run_id: request_id,
+            success: true,
+            output: ResolutionResult {
+                market_id,
+                winning_option,
+                void: false,
+                confidence_pct,
+                evidence_summary: "Verified".into(),
+            }
+            .encode(),
+        }
+    }
+
+    /// Contract with both agents and the callback origin configured
+    fn configured() -> PredictionMarket {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        contract.set_resolver_oracle(alice(), resolver_oracle()).unwrap();
+        contract.set_callback_origin(alice(), callback_origin()).unwrap();
+        contract
+    }
+
+    /// Create a two-option market with deadline 100
+    fn create_binary(contract: &mut PredictionMarket) -> MarketId {
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap()
+    }
+
+    /// Request resolution of a market and deliver a successful oracle callback
+    fn resolve(contract: &mut PredictionMarket, market_id: MarketId, winning_option: OptionIndex) {
+        let request_id = 1000 + market_id;
+        contract.request_resolution(market_id, 100, &mut queued(request_id)).unwrap();
+        deliver(contract, callback_origin(), resolution_callback(request_id, market_id, winning_option)).unwrap();
+    }
+
+    /// Deliver an oracle callback as `caller`, retrying failed runs from request ID 9000
+    fn deliver(contract: &mut PredictionMarket, caller: AccountId, payload: AgentCallbackPayload) -> Result<(), Error> {
+        contract.on_resolution_complete(caller, payload, 100, &mut queued(9000))
+    }
+
+    /// Deliver a successful oracle callback for an already requested market
+    fn resolve_pending(contract: &mut PredictionMarket, request_id: u64, market_id: MarketId, winning_option: OptionIndex) {
+        deliver(contract, callback_origin(), resolution_callback(request_id, market_id, winning_option)).unwrap();
+        assert_eq!(contract.get_market(market_id).unwrap().winning_option, Some(winning_option));
+    }
+
+    /// Build raw call data: selector followed by SCALE-encoded arguments
+    fn call_data<A: Encode>(selector: [u8; 4], args: A) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend_from_slice(&args.encode());
+        data
+    }
+
+    /// Feed raw call data through the dispatcher as `caller` at `block_number`
+    fn call_as(
+        contract: &mut PredictionMarket,
+        caller: AccountId,
+        block_number: BlockNumber,
+        input: &[u8],
+    ) -> DispatchOutput {
+        pay_as(contract, caller, block_number, 0, input)
+    }
+
+    /// Like [`call_as`], transferring `value` with the call
+    fn pay_as(
+        contract: &mut PredictionMarket,
+        caller: AccountId,
+        block_number: BlockNumber,
+        value: Balance,
+        input: &[u8],
+    ) -> DispatchOutput {
+        let ctx = CallContext {
+            caller,
+            block_number,
+            value_transferred: value,
+        };
+        contract.dispatch(&ctx, input, &mut queued(1))
+    }
+
+    #[test]
+    fn test_constructor() {
+        let contract = PredictionMarket::new(alice());
+        assert_eq!(contract.config.admin, alice());
+        assert!(contract.config.market_creator_agent.is_none());
+        assert!(contract.config.resolver_agents.is_empty());
+    }
+
+    #[test]
+    fn test_set_agents() {
+        let mut contract = PredictionMarket::new(alice());
+        
+        // Admin can set agents
+        assert!(contract.set_market_creator(alice(), market_creator()).is_ok());
+        assert!(contract.set_resolver_oracle(alice(), resolver_oracle()).is_ok());
+        
+        assert_eq!(contract.config.market_creator_agent, Some(market_creator()));
+        assert_eq!(contract.config.resolver_agents, vec![resolver_oracle()]);
+        
+        // Non-admin cannot
+        assert!(contract.set_market_creator(bob(), bob()).is_err());
+    }
+
+    #[test]
+    fn test_create_binary_market() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        
+        // Binary market with Yes/No
+        let market_id = contract.create_market(
+            market_creator(),
+            "Will BTC hit 100k?".into(),
+            vec!["Yes".into(), "No".into()],
+            "Price >= $100,000 on CoinGecko".into(),
+            "https://coingecko.com".into(),
+            100,
+            0,
+        ).unwrap();
+        
+        assert_eq!(market_id, 0);
+        
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.question, "Will BTC hit 100k?");
+        assert_eq!(market.options.len(), 2);
+        assert!(market.is_binary());
+        assert_eq!(market.status, MarketStatus::Open);
+    }
+
+    #[test]
+    fn test_create_multi_option_market() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        
+        // Multi-option market
+        let market_id = contract.create_market(
+            market_creator(),
+            "Who will win the championship?".into(),
+            vec!["Team A".into(), "Team B".into(), "Team C".into(), "Draw".into()],
+            "Official tournament results".into(),
+            "https://tournament.com".into(),
+            1000,
+            0,
+        ).unwrap();
         
-        // Admin can set agents
-        assert!(contract.set_market_creator(alice(), market_creator()).is_ok());
-        assert!(contract.set_resolver_oracle(alice(), resolver_oracle()).is_ok());
+        let market = contract.get_market(market_id).unwrap();
+        assert_eq!(market.options.len(), 4);
+        assert!(!market.is_binary());
+    }
+
+    #[test]
+    fn test_place_bet_multi_option() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
         
-        assert_eq!(contract.config.market_creator_agent, Some(market_creator()));
-        assert_eq!(contract.config.resolver_oracle_agent, Some(resolver_oracle()));
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into(), "C".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap();
         
-        // Non-admin cannot
-        assert!(contract.set_market_creator(bob(), bob()).is_err());
+        // Place bets on different options
+        contract.place_bet(alice(), 0, 0, 100, 100, 0).unwrap(); // Alice bets on A
+        contract.place_bet(bob(), 0, 1, 200, 200, 0).unwrap();   // Bob bets on B
+        contract.place_bet(charlie(), 0, 2, 150, 150, 0).unwrap(); // Charlie bets on C
+        
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.shares_per_option[0], 100);
+        assert_eq!(market.shares_per_option[1], 200);
+        assert_eq!(market.shares_per_option[2], 150);
+        assert_eq!(market.total_pool().unwrap(), 450);
+        
+        let alice_pos = contract.get_position(0, alice());
+        assert_eq!(alice_pos.shares, vec![100, 0, 0]);
+    }
+
+    #[test]
+    fn test_implied_odds() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap();
+        
+        // No bets - equal odds
+        let odds = contract.get_implied_odds(0).unwrap();
+        assert_eq!(odds, vec![50, 50]);
+        
+        // After bets: 75% on A, 25% on B
+        contract.place_bet(alice(), 0, 0, 300, 300, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 100, 100, 0).unwrap();
+        
+        let odds = contract.get_implied_odds(0).unwrap();
+        assert_eq!(odds, vec![75, 25]);
+    }
+
+    #[test]
+    fn test_full_lifecycle_multi_option() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        contract.set_resolver_oracle(alice(), resolver_oracle()).unwrap();
+        contract.set_callback_origin(alice(), callback_origin()).unwrap();
+        
+        // Create market with 3 options
+        let market_id = contract.create_market(
+            market_creator(),
+            "Which team wins?".into(),
+            vec!["Team A".into(), "Team B".into(), "Draw".into()],
+            "Official results".into(),
+            "tournament.com".into(),
+            100,
+            0,
+        ).unwrap();
+        
+        // Place bets
+        contract.place_bet(alice(), market_id, 0, 100, 100, 0).unwrap();   // Team A
+        contract.place_bet(bob(), market_id, 1, 100, 100, 0).unwrap();     // Team B
+        contract.place_bet(charlie(), market_id, 2, 100, 100, 0).unwrap(); // Draw
+        
+        // Request resolution (after deadline)
+        let mut ext = queued(1);
+        let receipts = contract.request_resolution(market_id, 101, &mut ext).unwrap();
+        assert_eq!(receipts[0].request_id, 1);
+        let request = &ext.queued[0];
+        assert_eq!(request.target_agent, resolver_oracle());
+        assert_eq!(request.callback.as_ref().unwrap().selector, selectors::ON_RESOLUTION_COMPLETE);
+        
+        // Simulate callback (Team B wins - option index 1)
+        let result = ResolutionResult {
+            market_id,
+            winning_option: 1, // Team B
+            void: false,
+            confidence_pct: 95,
+            evidence_summary: "Team B won 3-1".into(),
+        };
+        
+        let callback = AgentCallbackPayload {
+            request_id: 1,
+            run_id: 1,
+            success: true,
+            output: result.encode(),
+        };
+        
+        deliver(&mut contract, callback_origin(), callback).unwrap();
+        
+        let market = contract.get_market(market_id).unwrap();
+        assert_eq!(market.status, MarketStatus::Resolved);
+        assert_eq!(market.winning_option, Some(1));
+        
+        // Bob (Team B) wins - gets entire pool
+        let bob_payout = contract.claim_winnings(bob(), market_id).unwrap();
+        assert_eq!(bob_payout, 300); // Gets entire pool
+        
+        // Others have no winning shares
+        assert!(contract.claim_winnings(alice(), market_id).is_err());
+        assert!(contract.claim_winnings(charlie(), market_id).is_err());
+    }
+
+    #[test]
+    fn test_invalid_option_index() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap();
+        
+        // Try to bet on non-existent option
+        assert!(contract.place_bet(alice(), 0, 5, 100, 100, 0).is_err());
+    }
+
+    #[test]
+    fn test_dispatch_constructor() {
+        let contract = PredictionMarket::instantiate(MemoryStorage::default(), &call_data(selectors::CONSTRUCTOR, alice())).unwrap();
+        assert_eq!(contract.config.admin, alice());
+
+        assert!(PredictionMarket::instantiate(MemoryStorage::default(), &call_data(selectors::CREATE_MARKET, alice())).is_err());
+        assert!(PredictionMarket::instantiate(MemoryStorage::default(), &selectors::CONSTRUCTOR).is_err());
+    }
+
+    #[test]
+    fn test_dispatch_full_lifecycle() {
+        let mut contract = PredictionMarket::instantiate(MemoryStorage::default(), &call_data(selectors::CONSTRUCTOR, alice())).unwrap();
+
+        let out = call_as(&mut contract, alice(), 1, &call_data(selectors::SET_MARKET_CREATOR, market_creator()));
+        assert!(!out.revert);
+        let out = call_as(&mut contract, alice(), 1, &call_data(selectors::SET_RESOLVER_ORACLE, resolver_oracle()));
+        assert!(!out.revert);
+        let out = call_as(&mut contract, alice(), 1, &call_data(selectors::SET_CALLBACK_ORIGIN, callback_origin()));
+        assert!(!out.revert);
+
+        let create = call_data(
+            selectors::CREATE_MARKET,
+            (
+                String::from("Will BTC hit 100k?"),
+                vec![String::from("Yes"), String::from("No")],
+                String::from("Price >= $100,000 on CoinGecko"),
+                String::from("https://coingecko.com"),
+                100u64,
+                0u16,
+            ),
+        );
+        let out = call_as(&mut contract, market_creator(), 1, &create);
+        assert!(!out.revert);
+        assert_eq!(Result::<MarketId, Error>::decode(&mut &out.data[..]).unwrap(), Ok(0));
+
+        let out = pay_as(&mut contract, bob(), 2, 500, &call_data(selectors::PLACE_BET, (0u64, 0u8, 500u128)));
+        assert!(!out.revert);
+        let out = pay_as(&mut contract, charlie(), 2, 250, &call_data(selectors::PLACE_BET, (0u64, 1u8, 250u128)));
+        assert!(!out.revert);
+
+        let out = call_as(&mut contract, bob(), 3, &call_data(selectors::GET_POSITION, (0u64, bob())));
+        assert_eq!(Position::decode(&mut &out.data[..]).unwrap().shares, vec![500, 0]);
+
+        let out = call_as(&mut contract, bob(), 3, &call_data(selectors::GET_IMPLIED_ODDS, 0u64));
+        assert_eq!(Option::<Vec<u8>>::decode(&mut &out.data[..]).unwrap(), Some(vec![66, 33]));
+
+        let out = call_as(&mut contract, bob(), 101, &call_data(selectors::REQUEST_RESOLUTION, 0u64));
+        assert!(!out.revert);
+        assert_eq!(Result::<Vec<AgentRunReceipt>, Error>::decode(&mut &out.data[..]).unwrap(), Ok(vec![receipt(1)]));
+
+        let out = call_as(&mut contract, bob(), 101, &call_data(selectors::GET_PENDING_RESOLUTION, 0u64));
+        assert_eq!(
+            Option::<PendingResolution>::decode(&mut &out.data[..]).unwrap(),
+            Some(PendingResolution {
+                runs: vec![ResolverRun { resolver: resolver_oracle(), receipt: receipt(1), status: RunStatus::Outstanding }],
+                unsubmitted: 0,
+                quorum: 1,
+                min_confidence_pct: 0,
+                low_confidence_action: LowConfidenceAction::ReRequest,
+                expires_at: 101 + RESOLUTION_TTL_BLOCKS as BlockNumber,
+            })
+        );
+
+        let payload = AgentCallbackPayload {
+            request_id: 1,
+            run_id: 1,
+            success: true,
+            output: ResolutionResult {
+                market_id: 0,
+                winning_option: 0,
+                void: false,
+                confidence_pct: 90,
+                evidence_summary: "BTC at $101k".into(),
+            }
+            .encode(),
+        };
+        let out = call_as(&mut contract, callback_origin(), 102, &call_data(selectors::ON_RESOLUTION_COMPLETE, payload));
+        assert!(!out.revert);
+
+        let out = call_as(&mut contract, bob(), 103, &call_data(selectors::CLAIM_WINNINGS, 0u64));
+        assert!(!out.revert);
+        assert_eq!(Result::<Balance, Error>::decode(&mut &out.data[..]).unwrap(), Ok(750));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: bob(), amount: 750 }]);
+
+        let out = call_as(&mut contract, bob(), 103, &call_data(selectors::GET_MARKET, 0u64));
+        let market = Option::<Market>::decode(&mut &out.data[..]).unwrap().unwrap();
+        assert_eq!(market.status, MarketStatus::Resolved);
+        assert_eq!(market.winning_option, Some(0));
+
+        // Receipt is dropped once the callback is applied
+        let out = call_as(&mut contract, bob(), 103, &call_data(selectors::GET_PENDING_RESOLUTION, 0u64));
+        assert_eq!(Option::<PendingResolution>::decode(&mut &out.data[..]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_dispatch_reverts_on_error() {
+        let mut contract = PredictionMarket::instantiate(MemoryStorage::default(), &call_data(selectors::CONSTRUCTOR, alice())).unwrap();
+
+        // Method error is returned as an encoded Err and reverts
+        let out = call_as(&mut contract, bob(), 1, &call_data(selectors::SET_MARKET_CREATOR, bob()));
+        assert!(out.revert);
+        assert_eq!(
+            Result::<(), Error>::decode(&mut &out.data[..]).unwrap(),
+            Err(Error::NotAuthorized)
+        );
+
+        // Unknown selector
+        let out = call_as(&mut contract, bob(), 1, &call_data([0xff; 4], ()));
+        assert!(out.revert);
+
+        // Truncated selector
+        let out = call_as(&mut contract, bob(), 1, &[0x02, 0x00]);
+        assert!(out.revert);
+
+        // Malformed arguments
+        let out = call_as(&mut contract, bob(), 1, &call_data(selectors::PLACE_BET, 0u8));
+        assert!(out.revert);
+        assert_eq!(
+            Result::<(), Error>::decode(&mut &out.data[..]).unwrap(),
+            Err(Error::InvalidArguments)
+        );
+    }
+
+    #[test]
+    fn test_state_reload_from_storage() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap();
+        contract.place_bet(bob(), 0, 1, 42, 42, 0).unwrap();
+
+        let restored = PredictionMarket::load(contract.into_storage()).unwrap();
+        assert_eq!(restored.next_market_id, 1);
+        assert_eq!(restored.config.market_creator_agent, Some(market_creator()));
+        assert_eq!(restored.get_market(0).unwrap().shares_per_option, vec![0, 42]);
+        assert_eq!(restored.get_position(0, bob()).shares, vec![0, 42]);
+
+        // Uninitialized storage has no contract
+        assert!(PredictionMarket::load(MemoryStorage::default()).is_none());
+    }
+
+    #[test]
+    fn test_claim_clears_position() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        resolve(&mut contract, 0, 0);
+
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(100));
+        assert!(contract.get_position(0, bob()).is_empty());
+        assert_eq!(contract.claim_winnings(bob(), 0), Err(Error::NoPosition));
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        let mut contract = PredictionMarket::new(alice());
+        let create = |contract: &mut PredictionMarket, caller| contract.create_market(
+            caller,
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            0,
+        );
+
+        assert_eq!(create(&mut contract, market_creator()), Err(Error::MarketCreatorNotConfigured));
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        assert_eq!(create(&mut contract, bob()), Err(Error::NotAuthorized));
+        create(&mut contract, market_creator()).unwrap();
+
+        assert_eq!(contract.place_bet(bob(), 7, 0, 100, 100, 0), Err(Error::MarketNotFound));
+        assert_eq!(contract.place_bet(bob(), 0, 2, 100, 100, 0), Err(Error::InvalidOptionIndex));
+        assert_eq!(contract.request_resolution(0, 99, &mut queued(1)), Err(Error::DeadlineNotReached));
+        assert_eq!(contract.request_resolution(0, 100, &mut queued(1)), Err(Error::ResolverNotConfigured));
+        assert_eq!(contract.claim_winnings(bob(), 0), Err(Error::MarketNotResolved));
+    }
+
+    #[test]
+    fn test_error_encoding_is_stable() {
+        // The CLI decodes errors by variant index
+        assert_eq!(Error::UnknownSelector.encode(), vec![0]);
+        assert_eq!(Error::NotAuthorized.encode(), vec![2]);
+        assert_eq!(Error::WinningPoolEmpty.encode(), vec![17]);
+        assert_eq!(Error::ResolutionAttemptsExhausted.encode(), vec![53]);
+    }
+
+    #[test]
+    fn test_events_full_lifecycle() {
+        let mut contract = PredictionMarket::new(alice());
+        assert!(matches!(
+            contract.take_events()[..],
+            [Event::ConfigChanged { changed_by, .. }] if changed_by == alice()
+        ));
+
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        contract.set_resolver_oracle(alice(), resolver_oracle()).unwrap();
+        let events = contract.take_events();
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            Event::ConfigChanged { changed_by, config } => {
+                assert_eq!(*changed_by, alice());
+                assert_eq!(config.resolver_agents, vec![resolver_oracle()]);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let market_id = create_binary(&mut contract);
+        contract.place_bet(bob(), market_id, 1, 300, 300, 0).unwrap();
+        assert_eq!(
+            contract.take_events(),
+            vec![
+                Event::MarketCreated {
+                    market_id,
+                    creator: market_creator(),
+                    question: "Test?".into(),
+                    options: vec!["A".into(), "B".into()],
+                    resolution_deadline: 100,
+                },
+                Event::BetPlaced { market_id, account: bob(), option_index: 1, amount: 300 },
+            ]
+        );
+        contract.set_callback_origin(alice(), callback_origin()).unwrap();
+        contract.take_events();
+        resolve(&mut contract, market_id, 1);
+        contract.claim_winnings(bob(), market_id).unwrap();
+
+        assert_eq!(
+            contract.take_events(),
+            vec![
+                Event::ResolutionRequested { market_id, resolver: resolver_oracle(), request_id: 1000 + market_id },
+                Event::MarketResolved { market_id, winning_option: 1 },
+                Event::WinningsClaimed { market_id, account: bob(), payout: 300 },
+            ]
+        );
+        assert!(contract.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_event_topics() {
+        let bet = Event::BetPlaced { market_id: 7, account: bob(), option_index: 0, amount: 1 };
+        let topics = bet.topics();
+        assert_eq!(topics, vec![market_topic(7), bob()]);
+        assert_ne!(market_topic(7), market_topic(8));
+
+        let resolved = Event::MarketResolved { market_id: 7, winning_option: 0 };
+        assert_eq!(resolved.topics(), vec![market_topic(7)]);
+    }
+
+    #[test]
+    fn test_event_indices_are_stable() {
+        // Indexers decode events by variant index: pin the indices that ship
+        let claimed = Event::WinningsClaimed { market_id: 7, account: bob(), payout: 1 };
+        let config = Box::new(PredictionMarket::new(alice()).get_config().clone());
+        assert_eq!(Event::MarketResolved { market_id: 7, winning_option: 0 }.encode()[0], 3);
+        assert_eq!(claimed.encode()[0], 4);
+        assert_eq!(Event::ConfigChanged { changed_by: alice(), config }.encode()[0], 5);
+        assert_eq!(Event::FeesWithdrawn { recipient: bob(), amount: 1 }.encode()[0], 11);
+        assert_eq!(Event::MarketCancelled { market_id: 7, cancelled_by: alice() }.encode()[0], 18);
+        let bought = Event::SharesBought { market_id: 7, account: bob(), option_index: 0, shares: 1, cost: 1 };
+        assert_eq!(bought.encode()[0], 19);
+        let withdrawn = Event::BetWithdrawn { market_id: 7, account: bob(), option_index: 0, amount: 1, penalty: 0 };
+        assert_eq!(withdrawn.encode()[0], 22);
+        let approval = Event::Approval { market_id: 7, owner: bob(), spender: alice(), option_index: 0, amount: 1 };
+        assert_eq!(approval.encode()[0], 24);
+        let limits = Event::MarketBetLimitsChanged { market_id: 7, changed_by: alice(), bet_limits: None };
+        assert_eq!(limits.encode()[0], 26);
+    }
+
+    #[test]
+    fn test_reverted_call_discards_events() {
+        let mut contract = PredictionMarket::instantiate(MemoryStorage::default(), &call_data(selectors::CONSTRUCTOR, alice())).unwrap();
+        contract.take_events();
+
+        let out = call_as(&mut contract, bob(), 1, &call_data(selectors::SET_MARKET_CREATOR, bob()));
+        assert!(out.revert);
+        assert!(contract.take_events().is_empty());
+
+        let out = call_as(&mut contract, alice(), 1, &call_data(selectors::SET_MARKET_CREATOR, market_creator()));
+        assert!(!out.revert);
+        assert_eq!(contract.take_events().len(), 1);
+    }
+
+    #[test]
+    fn test_callback_rejects_forged_caller() {
+        let mut contract = PredictionMarket::new(alice());
+        contract.set_market_creator(alice(), market_creator()).unwrap();
+        contract.set_resolver_oracle(alice(), resolver_oracle()).unwrap();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(5)).unwrap();
+
+        // No callback origin configured: nobody can deliver
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(5, 0, 0)),
+            Err(Error::NotAuthorized)
+        );
+
+        contract.set_callback_origin(alice(), callback_origin()).unwrap();
+        for forger in [bob(), resolver_oracle(), alice()] {
+            assert_eq!(
+                deliver(&mut contract, forger, resolution_callback(5, 0, 0)),
+                Err(Error::NotAuthorized)
+            );
+        }
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+
+        // Only admin can set the origin
+        assert_eq!(contract.set_callback_origin(bob(), bob()), Err(Error::NotAuthorized));
+    }
+
+    #[test]
+    fn test_callback_requires_recorded_request_id() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(5)).unwrap();
+        assert_eq!(contract.get_pending_resolution(0).map(|pending| pending.receipts()), Some(vec![receipt(5)]));
+
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(6, 0, 0)),
+            Err(Error::RequestIdMismatch)
+        );
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+
+        assert_eq!(deliver(&mut contract, callback_origin(), resolution_callback(5, 0, 1)), Ok(()));
+        assert_eq!(contract.get_market(0).unwrap().winning_option, Some(1));
+        assert_eq!(contract.get_pending_resolution(0), None);
+    }
+
+    #[test]
+    fn test_callback_for_other_market_rejected() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(5)).unwrap();
+        contract.request_resolution(1, 100, &mut queued(6)).unwrap();
+
+        // Market 1's request ID cannot resolve market 0; it counts as a failed run of market 1
+        assert_eq!(deliver(&mut contract, callback_origin(), resolution_callback(6, 0, 0)), Ok(()));
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+        assert_eq!(contract.get_pending_resolution(1).unwrap().runs[0].receipt.request_id, 9000);
+        resolve_pending(&mut contract, 9000, 1, 1);
+        resolve_pending(&mut contract, 5, 0, 0);
+    }
+
+    #[test]
+    fn test_callback_replay_rejected() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(5)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(5, 0, 0)).unwrap();
+
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(5, 0, 1)),
+            Err(Error::RequestAlreadyProcessed)
+        );
+        assert_eq!(contract.get_market(0).unwrap().winning_option, Some(0));
+    }
+
+    #[test]
+    fn test_rejected_agent_request_keeps_market_open() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.take_events();
+
+        let mut ext = queued(1);
+        for (failure, error) in [
+            (ChainExtError::AgentNotFound, Error::AgentNotFound),
+            (ChainExtError::AgentRejected, Error::AgentRequestRejected),
+            (ChainExtError::InsufficientFunds, Error::InsufficientAgentFunds),
+            (ChainExtError::QueueFull, Error::AgentRequestFailed),
+            (ChainExtError::MalformedReceipt, Error::AgentRequestFailed),
+        ] {
+            ext.fail_next(failure);
+            assert_eq!(contract.request_resolution(0, 100, &mut ext), Err(error));
+            assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Open);
+            assert_eq!(contract.get_pending_resolution(0), None);
+            assert!(contract.take_events().is_empty());
+        }
+
+        // Once the extension queues the run, resolution proceeds
+        assert_eq!(contract.request_resolution(0, 100, &mut ext), Ok(vec![receipt(1)]));
+        assert_eq!(contract.get_pending_resolution(0).map(|pending| pending.receipts()), Some(vec![receipt(1)]));
+    }
+
+    #[test]
+    fn test_dispatch_surfaces_chain_extension_errors() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+
+        let mut ext = queued(1);
+        ext.fail_next(ChainExtError::InsufficientFunds);
+        let ctx = CallContext {
+            caller: bob(),
+            block_number: 100,
+            value_transferred: 0,
+        };
+        let out = contract.dispatch(&ctx, &call_data(selectors::REQUEST_RESOLUTION, 0u64), &mut ext);
+        assert!(out.revert);
+        assert_eq!(
+            Result::<AgentRunReceipt, Error>::decode(&mut &out.data[..]).unwrap(),
+            Err(Error::InsufficientAgentFunds)
+        );
+    }
+
+    #[test]
+    fn test_bet_requires_matching_value() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 99, 0), Err(Error::StakeMismatch));
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 0, 0), Err(Error::StakeMismatch));
+        assert_eq!(contract.place_bet(bob(), 0, 0, 0, 0, 0), Err(Error::ZeroAmount));
+        assert_eq!(contract.get_total_escrowed(), 0);
+
+        // Through the dispatcher the stake is the transferred value
+        let bet = call_data(selectors::PLACE_BET, (0u64, 0u8, 100u128));
+        let out = pay_as(&mut contract, bob(), 1, 50, &bet);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::StakeMismatch));
+        assert!(!pay_as(&mut contract, bob(), 1, 100, &bet).revert);
+        assert_eq!(contract.get_total_escrowed(), 100);
+
+        // Other methods refuse value
+        let out = pay_as(&mut contract, bob(), 100, 5, &call_data(selectors::CLAIM_WINNINGS, 0u64));
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::NotPayable));
+    }
+
+    #[test]
+    fn test_claim_transfers_payout() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        contract.place_bet(charlie(), 0, 1, 300, 300, 0).unwrap();
+        resolve(&mut contract, 0, 0);
+        assert!(contract.take_transfers().is_empty());
+
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(400));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: bob(), amount: 400 }]);
+        assert_eq!(contract.get_market(0).unwrap().escrowed, 0);
+        assert_eq!(contract.get_total_escrowed(), 0);
+
+        // A reverted claim transfers nothing
+        let out = call_as(&mut contract, bob(), 100, &call_data(selectors::CLAIM_WINNINGS, 0u64));
+        assert!(out.revert);
+        assert!(contract.take_transfers().is_empty());
+    }
+
+    /// Check that the escrow total matches the per-market escrow, that open
+    /// markets hold exactly their pool (or LMSR cost), and that the contract's
+    /// balance covers it plus the fees awaiting withdrawal
+    fn assert_escrow_invariant(contract: &PredictionMarket, balance: Balance) {
+        let markets: Vec<Market> = (0..contract.next_market_id).filter_map(|id| contract.get_market(id)).collect();
+        let escrowed: Balance = markets.iter().map(|m| m.escrowed).sum();
+        assert_eq!(contract.get_total_escrowed(), escrowed);
+        let unsettled = |m: &&Market| matches!(m.status, MarketStatus::Open | MarketStatus::PendingResolution);
+        for market in markets.iter().filter(unsettled) {
+            match market.pricing {
+                Pricing::Parimutuel => assert_eq!(market.escrowed, market.total_pool().unwrap()),
+                Pricing::Lmsr { liquidity } => {
+                    assert_eq!(market.escrowed, lmsr::cost(&market.shares_per_option, liquidity).unwrap())
+                }
+            }
+        }
+        let fees = contract.get_accrued_fees() + contract.get_creator_fees(market_creator());
+        assert_eq!(balance, escrowed + fees);
+    }
+
+    #[test]
+    fn test_escrow_invariant() {
+        let mut contract = configured();
+        let mut balance: Balance = 0;
+        let bettors = [alice(), bob(), charlie()];
+
+        for _ in 0..3 {
+            create_binary(&mut contract);
+        }
+        for (i, bettor) in bettors.iter().enumerate() {
+            for market_id in 0..3 {
+                let amount = 100 * (i as Balance + 1) + market_id as Balance;
+                contract.place_bet(*bettor, market_id, (i % 2) as OptionIndex, amount, amount, 0).unwrap();
+                balance += amount;
+                assert_escrow_invariant(&contract, balance);
+            }
+        }
+
+        resolve(&mut contract, 0, 0);
+        resolve(&mut contract, 1, 1);
+        assert_escrow_invariant(&contract, balance);
+
+        for bettor in bettors {
+            for market_id in 0..2 {
+                let _ = contract.claim_winnings(bettor, market_id);
+                for transfer in contract.take_transfers() {
+                    balance -= transfer.amount;
+                }
+                assert_escrow_invariant(&contract, balance);
+            }
+        }
+
+        // Only rounding dust is left behind in resolved markets
+        assert_eq!(contract.get_market(2).unwrap().escrowed, contract.get_market(2).unwrap().total_pool().unwrap());
+        assert!(contract.get_market(0).unwrap().escrowed < bettors.len() as Balance);
+        assert!(contract.get_market(1).unwrap().escrowed < bettors.len() as Balance);
+    }
+
+    #[test]
+    fn test_failed_run_is_re_requested() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(5)).unwrap();
+        contract.take_events();
+
+        let mut ext = queued(6);
+        contract.on_resolution_complete(callback_origin(), failed_callback(5), 120, &mut ext).unwrap();
+        assert_eq!(ext.queued.len(), 1);
+        assert_eq!(
+            contract.take_events(),
+            vec![
+                Event::ResolutionFailed { market_id: 0, request_id: 5, attempt: 1 },
+                Event::ResolutionRequested { market_id: 0, resolver: resolver_oracle(), request_id: 6 },
+            ]
+        );
+        let pending = contract.get_pending_resolution(0).unwrap();
+        assert_eq!(pending.receipts(), vec![receipt(6)]);
+        assert_eq!(pending.expires_at, 120 + RESOLUTION_TTL_BLOCKS as BlockNumber);
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 2);
+
+        // The failed request is consumed; the new one resolves the market
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), failed_callback(5)),
+            Err(Error::RequestAlreadyProcessed)
+        );
+        resolve_pending(&mut contract, 6, 0, 1);
+    }
+
+    #[test]
+    fn test_unusable_output_counts_as_failed_run() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        let garbage = AgentCallbackPayload { output: vec![0xff], ..resolution_callback(1, 0, 0) };
+        deliver(&mut contract, callback_origin(), garbage).unwrap();
+        // Option 2 does not exist in a binary market
+        deliver(&mut contract, callback_origin(), resolution_callback(9000, 0, 2)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 3);
+    }
+
+    #[test]
+    fn test_re_requests_are_bounded() {
+        let mut contract = configured();
+        contract.set_max_resolution_attempts(alice(), 2).unwrap();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        let mut ext = queued(2);
+        contract.on_resolution_complete(callback_origin(), failed_callback(1), 100, &mut ext).unwrap();
+        contract.on_resolution_complete(callback_origin(), failed_callback(2), 100, &mut ext).unwrap();
+        assert_eq!(ext.queued.len(), 1);
+        assert_eq!(contract.get_pending_resolution(0), None);
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+
+        // With every round used, only the admin fallback can settle the market
+        let mut ext = queued(3);
+        assert_eq!(contract.request_resolution(0, 101, &mut ext), Err(Error::ResolutionAttemptsExhausted));
+        assert!(ext.queued.is_empty());
+        assert_eq!(contract.admin_resolve(alice(), 0, 0, 101), Ok(()));
+
+        assert_eq!(contract.set_max_resolution_attempts(alice(), 0), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_max_resolution_attempts(bob(), 5), Err(Error::NotAuthorized));
+    }
+
+    #[test]
+    fn test_refused_re_request_keeps_timed_out_round() {
+        let mut contract = configured();
+        contract.set_max_resolution_attempts(alice(), 1).unwrap();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        // A refused call changes nothing, even once the round has timed out
+        let expired = 100 + RESOLUTION_TTL_BLOCKS as BlockNumber;
+        assert_eq!(contract.request_resolution(0, expired, &mut queued(2)), Err(Error::ResolutionAttemptsExhausted));
+        assert_eq!(contract.get_pending_resolution(0).map(|pending| pending.receipts()), Some(vec![receipt(1)]));
+    }
+
+    #[test]
+    fn test_refused_re_request_leaves_market_retriggerable() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        let mut ext = queued(2);
+        ext.fail_next(ChainExtError::InsufficientFunds);
+        assert_eq!(contract.on_resolution_complete(callback_origin(), failed_callback(1), 100, &mut ext), Ok(()));
+        assert_eq!(contract.get_pending_resolution(0), None);
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 1);
+
+        contract.request_resolution(0, 100, &mut ext).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 2);
+    }
+
+    #[test]
+    fn test_timed_out_request_can_be_re_triggered() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        let expires_at = contract.get_pending_resolution(0).unwrap().expires_at;
+
+        assert_eq!(
+            contract.request_resolution(0, expires_at - 1, &mut queued(2)),
+            Err(Error::ResolutionInProgress)
+        );
+        assert_eq!(contract.request_resolution(0, expires_at, &mut queued(2)).map(|r| r[0].request_id), Ok(2));
+
+        // The late callback of the timed-out run is no longer accepted
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)),
+            Err(Error::RequestIdMismatch)
+        );
+        resolve_pending(&mut contract, 2, 0, 1);
+    }
+
+    #[test]
+    fn test_admin_fallback_resolution() {
+        let mut contract = configured();
+        contract.set_max_resolution_attempts(alice(), 1).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 1, 100, 100, 0).unwrap();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        // Not while a run is outstanding, and never by anyone but the admin
+        assert_eq!(contract.admin_resolve(alice(), 0, 1, 100), Err(Error::ResolutionInProgress));
+        deliver(&mut contract, callback_origin(), failed_callback(1)).unwrap();
+        assert_eq!(contract.admin_resolve(bob(), 0, 1, 100), Err(Error::NotAuthorized));
+        assert_eq!(contract.admin_resolve(alice(), 0, 2, 100), Err(Error::InvalidOptionIndex));
+
+        contract.take_events();
+        contract.admin_resolve(alice(), 0, 1, 100).unwrap();
+        assert_eq!(contract.take_events(), vec![Event::MarketResolved { market_id: 0, winning_option: 1 }]);
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(100));
+        assert_eq!(contract.admin_resolve(alice(), 0, 0, 100), Err(Error::MarketNotPendingResolution));
+    }
+
+    #[test]
+    fn test_admin_fallback_after_timeout() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        let expires_at = contract.get_pending_resolution(0).unwrap().expires_at;
+
+        assert_eq!(contract.void_market(alice(), 0, expires_at - 1), Err(Error::ResolutionInProgress));
+        contract.void_market(alice(), 0, expires_at).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Voided);
+        assert_eq!(contract.get_pending_resolution(0), None);
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)),
+            Err(Error::RequestIdMismatch)
+        );
+    }
+
+    #[test]
+    fn test_voided_market_refunds_stakes() {
+        let mut contract = configured();
+        contract.set_max_resolution_attempts(alice(), 1).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 50, 50, 0).unwrap();
+        contract.place_bet(charlie(), 0, 1, 300, 300, 0).unwrap();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), failed_callback(1)).unwrap();
+
+        assert_eq!(contract.claim_refund(bob(), 0), Err(Error::MarketNotVoided));
+        assert_eq!(contract.void_market(bob(), 0, 100), Err(Error::NotAuthorized));
+        contract.void_market(alice(), 0, 100).unwrap();
+        contract.take_events();
+        contract.take_transfers();
+
+        assert_eq!(contract.claim_winnings(bob(), 0), Err(Error::MarketNotResolved));
+        assert_eq!(contract.claim_refund(bob(), 0), Ok(150));
+        assert_eq!(contract.claim_refund(charlie(), 0), Ok(300));
+        assert_eq!(contract.claim_refund(bob(), 0), Err(Error::NoPosition));
+        assert_eq!(
+            contract.take_transfers(),
+            vec![Transfer { to: bob(), amount: 150 }, Transfer { to: charlie(), amount: 300 }]
+        );
+        assert_eq!(
+            contract.take_events()[0],
+            Event::RefundClaimed { market_id: 0, account: bob(), amount: 150 }
+        );
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_resolver_can_void_market() {
+        let mut contract = configured();
+        let market_id = contract.create_market(
+            market_creator(),
+            "Who wins?".into(),
+            vec!["A".into(), "B".into(), "Draw".into()],
+            "Official result".into(),
+            "Source".into(),
+            100,
+            0,
+        ).unwrap();
+        contract.place_bet(alice(), market_id, 0, 100, 100, 0).unwrap();
+        contract.place_bet(alice(), market_id, 2, 25, 25, 0).unwrap();
+        contract.place_bet(bob(), market_id, 1, 200, 200, 0).unwrap();
+        contract.request_resolution(market_id, 100, &mut queued(1)).unwrap();
+        contract.take_events();
+
+        // The winning option is ignored when voiding, even if out of range
+        let void = ResolutionResult {
+            market_id,
+            winning_option: 99,
+            void: true,
+            confidence_pct: 90,
+            evidence_summary: "Match cancelled".into(),
+        };
+        let payload = AgentCallbackPayload { output: void.encode(), ..resolution_callback(1, market_id, 0) };
+        deliver(&mut contract, callback_origin(), payload).unwrap();
+
+        let market = contract.get_market(market_id).unwrap();
+        assert_eq!(market.status, MarketStatus::Voided);
+        assert_eq!(market.winning_option, None);
+        assert_eq!(contract.take_events(), vec![Event::MarketVoided { market_id }]);
+
+        assert_eq!(contract.claim_winnings(alice(), market_id), Err(Error::MarketNotResolved));
+        assert_eq!(contract.claim_refund(alice(), market_id), Ok(125));
+        assert_eq!(contract.claim_refund(bob(), market_id), Ok(200));
+        assert_eq!(contract.claim_refund(charlie(), market_id), Err(Error::NoPosition));
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_admin_can_void_open_market() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+
+        assert_eq!(contract.void_market(bob(), 0, 10), Err(Error::NotAuthorized));
+        contract.void_market(alice(), 0, 10).unwrap();
+
+        // Voided markets take no more bets or resolution requests
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 100, 0), Err(Error::MarketNotOpen));
+        assert_eq!(contract.request_resolution(0, 100, &mut queued(1)), Err(Error::MarketNotOpen));
+        assert_eq!(contract.void_market(alice(), 0, 10), Err(Error::MarketNotPendingResolution));
+        assert_eq!(contract.claim_refund(bob(), 0), Ok(100));
+
+        // Resolved markets cannot be voided
+        create_binary(&mut contract);
+        resolve(&mut contract, 1, 0);
+        assert_eq!(contract.void_market(alice(), 1, 100), Err(Error::MarketNotPendingResolution));
+    }
+
+    #[test]
+    fn test_betting_closes_at_deadline() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        assert_eq!(contract.get_market(0).unwrap().betting_closes_at, 100);
+
+        contract.place_bet(bob(), 0, 0, 100, 100, 99).unwrap();
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 100, 100), Err(Error::BettingClosed));
+        assert_eq!(contract.place_bet(charlie(), 0, 1, 100, 100, 150), Err(Error::BettingClosed));
+
+        // Late bets through the dispatcher revert and escrow nothing
+        let bet = call_data(selectors::PLACE_BET, (0u64, 1u8, 100u128));
+        let out = pay_as(&mut contract, charlie(), 101, 100, &bet);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::BettingClosed));
+        assert_eq!(contract.get_total_escrowed(), 100);
+        assert_eq!(contract.get_market(0).unwrap().total_pool().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_configurable_betting_cutoff() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        assert_eq!(contract.set_betting_cutoff(bob(), 10), Err(Error::NotAuthorized));
+        contract.set_betting_cutoff(alice(), 10).unwrap();
+        create_binary(&mut contract);
+
+        // Only markets created afterwards close early
+        assert_eq!(contract.get_market(0).unwrap().betting_closes_at, 100);
+        assert_eq!(contract.get_market(1).unwrap().betting_closes_at, 90);
+        contract.place_bet(bob(), 0, 0, 100, 100, 95).unwrap();
+        contract.place_bet(bob(), 1, 0, 100, 100, 89).unwrap();
+        assert_eq!(contract.place_bet(bob(), 1, 0, 100, 100, 90), Err(Error::BettingClosed));
+
+        // A cutoff longer than the market's lifetime closes betting from the start
+        contract.set_betting_cutoff(alice(), 1_000).unwrap();
+        create_binary(&mut contract);
+        assert_eq!(contract.place_bet(bob(), 2, 0, 100, 100, 0), Err(Error::BettingClosed));
+    }
+
+    fn treasury() -> AccountId {
+        [20u8; 32]
+    }
+
+    #[test]
+    fn test_no_winner_pool_refunded_by_default() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        contract.place_bet(charlie(), 0, 0, 50, 50, 0).unwrap();
+        resolve(&mut contract, 0, 1);
+
+        assert_eq!(contract.get_market(0).unwrap().no_winner_policy, Some(NoWinnerPolicy::Refund));
+        assert_eq!(contract.claim_winnings(bob(), 0), Err(Error::WinningPoolEmpty));
+        assert_eq!(contract.get_claimable(0, bob()), Claimable::Refund(100));
+        assert_eq!(contract.sweep_to_treasury(0), Err(Error::NotTreasuryPool));
+
+        assert_eq!(contract.claim_refund(bob(), 0), Ok(100));
+        assert_eq!(contract.claim_refund(charlie(), 0), Ok(50));
+        assert_eq!(contract.get_claimable(0, bob()), Claimable::Nothing);
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_no_winner_pool_to_treasury() {
+        let mut contract = configured();
+        assert_eq!(
+            contract.set_no_winner_policy(alice(), NoWinnerPolicy::Treasury),
+            Err(Error::TreasuryNotConfigured)
+        );
+        assert_eq!(contract.set_treasury(bob(), bob()), Err(Error::NotAuthorized));
+        contract.set_treasury(alice(), treasury()).unwrap();
+        contract.set_no_winner_policy(alice(), NoWinnerPolicy::Treasury).unwrap();
+
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        resolve(&mut contract, 0, 1);
+
+        // Changing the policy later does not affect a resolved market
+        contract.set_no_winner_policy(alice(), NoWinnerPolicy::Refund).unwrap();
+        assert_eq!(contract.get_claimable(0, bob()), Claimable::PoolToTreasury);
+        assert_eq!(contract.claim_refund(bob(), 0), Err(Error::MarketNotVoided));
+
+        contract.take_events();
+        assert_eq!(contract.sweep_to_treasury(0), Ok(100));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: treasury(), amount: 100 }]);
+        assert_eq!(
+            contract.take_events(),
+            vec![Event::PoolSweptToTreasury { market_id: 0, treasury: treasury(), amount: 100 }]
+        );
+        assert_eq!(contract.sweep_to_treasury(0), Err(Error::NotTreasuryPool));
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_get_claimable() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 100, 100, 0).unwrap();
+        contract.place_bet(charlie(), 0, 1, 300, 300, 0).unwrap();
+        assert_eq!(contract.get_claimable(0, bob()), Claimable::NotSettled);
+        assert_eq!(contract.get_claimable(9, bob()), Claimable::Nothing);
+
+        resolve(&mut contract, 0, 0);
+        assert_eq!(contract.get_claimable(0, bob()), Claimable::Winnings(400));
+        assert_eq!(contract.get_claimable(0, charlie()), Claimable::Nothing);
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::Nothing);
+
+        contract.claim_winnings(bob(), 0).unwrap();
+        let out = call_as(&mut contract, bob(), 100, &call_data(selectors::GET_CLAIMABLE, (0u64, bob())));
+        assert_eq!(Claimable::decode(&mut &out.data[..]).unwrap(), Claimable::Nothing);
+
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 1, 1, 70, 70, 0).unwrap();
+        contract.void_market(alice(), 1, 10).unwrap();
+        assert_eq!(contract.get_claimable(1, bob()), Claimable::Refund(70));
+    }
+
+    #[test]
+    fn test_fees_taken_from_losing_side() {
+        let mut contract = configured();
+        contract.set_treasury(alice(), treasury()).unwrap();
+        contract.set_protocol_fee(alice(), 200).unwrap();
+        contract.create_market(
+            market_creator(),
+            "Test?".into(),
+            vec!["A".into(), "B".into()],
+            "Criteria".into(),
+            "Source".into(),
+            100,
+            100,
+        ).unwrap();
+        // Existing markets keep the fee they were created with
+        contract.set_protocol_fee(alice(), 0).unwrap();
+
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 3000, 3000, 0).unwrap();
+        contract.take_events();
+        resolve(&mut contract, 0, 0);
+
+        // 2% protocol and 1% creator fee on the 3000 losing stake
+        assert!(contract.take_events().contains(&Event::FeesAccrued {
+            market_id: 0,
+            protocol_fee: 60,
+            creator: market_creator(),
+            creator_fee: 30,
+        }));
+        assert_eq!(contract.get_accrued_fees(), 60);
+        assert_eq!(contract.get_creator_fees(market_creator()), 30);
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::Winnings(3910));
+        assert_escrow_invariant(&contract, 4000);
+
+        assert_eq!(contract.claim_winnings(alice(), 0), Ok(3910));
+        assert_escrow_invariant(&contract, 90);
+
+        contract.take_transfers();
+        assert_eq!(contract.withdraw_fees(bob()), Err(Error::NotAuthorized));
+        assert_eq!(contract.withdraw_fees(alice()), Ok(60));
+        assert_eq!(contract.withdraw_fees(alice()), Err(Error::NoFeesAccrued));
+        assert_eq!(contract.withdraw_creator_fees(market_creator()), Ok(30));
+        assert_eq!(contract.withdraw_creator_fees(market_creator()), Err(Error::NoFeesAccrued));
+        assert_eq!(
+            contract.take_transfers(),
+            vec![Transfer { to: treasury(), amount: 60 }, Transfer { to: market_creator(), amount: 30 }]
+        );
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_fee_limits() {
+        let mut contract = configured();
+        assert_eq!(contract.set_protocol_fee(alice(), 100), Err(Error::TreasuryNotConfigured));
+        contract.set_treasury(alice(), treasury()).unwrap();
+        assert_eq!(contract.set_protocol_fee(bob(), 100), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_protocol_fee(alice(), MAX_PROTOCOL_FEE_BPS + 1), Err(Error::FeeTooHigh));
+        assert_eq!(contract.set_protocol_fee(alice(), MAX_PROTOCOL_FEE_BPS), Ok(()));
+
+        let create = call_data(
+            selectors::CREATE_MARKET,
+            (
+                String::from("Test?"),
+                vec![String::from("A"), String::from("B")],
+                String::from("Criteria"),
+                String::from("Source"),
+                100u64,
+                MAX_CREATOR_FEE_BPS + 1,
+            ),
+        );
+        let out = call_as(&mut contract, market_creator(), 1, &create);
+        assert!(out.revert);
+        assert_eq!(Result::<MarketId, Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::FeeTooHigh));
+    }
+
+    #[test]
+    fn test_refunds_are_fee_free() {
+        let mut contract = configured();
+        contract.set_treasury(alice(), treasury()).unwrap();
+        contract.set_protocol_fee(alice(), 500).unwrap();
+
+        // Nobody won market 0, market 1 is voided
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.place_bet(bob(), 1, 1, 500, 500, 0).unwrap();
+        resolve(&mut contract, 0, 1);
+        contract.void_market(alice(), 1, 0).unwrap();
+
+        assert_eq!(contract.get_accrued_fees(), 0);
+        assert_eq!(contract.claim_refund(alice(), 0), Ok(1000));
+        assert_eq!(contract.claim_refund(bob(), 1), Ok(500));
+        assert_eq!(contract.withdraw_fees(alice()), Err(Error::NoFeesAccrued));
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    /// Contract with a 50-block dispute window and a bond of 100
+    fn with_dispute_window() -> PredictionMarket {
+        let mut contract = configured();
+        contract.set_dispute_window(alice(), 50, 100).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 1000, 1000, 0).unwrap();
+        contract
+    }
+
+    #[test]
+    fn test_proposed_outcome_final_after_window() {
+        let mut contract = with_dispute_window();
+        contract.take_events();
+        resolve(&mut contract, 0, 0);
+
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.status, MarketStatus::Proposed);
+        assert!(contract.take_events().contains(&Event::ResolutionProposed {
+            market_id: 0,
+            outcome: Outcome::Winner(0),
+            dispute_ends_at: 150,
+        }));
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::NotSettled);
+        assert_eq!(contract.claim_winnings(alice(), 0), Err(Error::MarketNotResolved));
+
+        assert_eq!(contract.finalize_resolution(0, 149), Err(Error::DisputeWindowOpen));
+        assert_eq!(contract.finalize_resolution(0, 150), Ok(()));
+        assert_eq!(contract.finalize_resolution(0, 150), Err(Error::MarketNotProposed));
+        assert_eq!(contract.dispute_resolution(bob(), 0, 100, 150, &mut queued(2000)), Err(Error::MarketNotProposed));
+        assert_eq!(contract.claim_winnings(alice(), 0), Ok(2000));
+    }
+
+    #[test]
+    fn test_dispute_window_validated() {
+        let mut contract = configured();
+        assert_eq!(contract.set_dispute_window(bob(), 50, 100), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_dispute_window(alice(), 50, 0), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_dispute_window(alice(), 50, 1), Ok(()));
+        assert_eq!(contract.set_dispute_window(alice(), 0, 0), Ok(()));
+        assert_eq!(contract.get_config().dispute_window_blocks, 0);
+    }
+
+    #[test]
+    fn test_dispute_rejected_by_second_run_slashes_bond() {
+        let mut contract = with_dispute_window();
+        resolve(&mut contract, 0, 0);
+
+        assert_eq!(contract.dispute_resolution(charlie(), 0, 100, 120, &mut queued(2000)), Err(Error::NoPosition));
+        assert_eq!(contract.dispute_resolution(bob(), 0, 50, 120, &mut queued(2000)), Err(Error::StakeMismatch));
+        assert_eq!(contract.dispute_resolution(bob(), 0, 100, 150, &mut queued(2000)), Err(Error::DisputeWindowClosed));
+
+        // The bond is paid with the call
+        let dispute = call_data(selectors::DISPUTE_RESOLUTION, 0u64);
+        assert!(!pay_as(&mut contract, bob(), 120, 100, &dispute).revert);
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Disputed);
+        assert_eq!(contract.get_pending_resolution(0).map(|pending| pending.receipts()), Some(vec![receipt(1)]));
+        assert_eq!(contract.dispute_resolution(alice(), 0, 100, 121, &mut queued(2000)), Err(Error::AlreadyDisputed));
+        assert_escrow_invariant(&contract, 2100);
+
+        // The second run agrees with the proposal
+        contract.take_events();
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)).unwrap();
+        assert!(contract.take_events().contains(&Event::DisputeSettled { market_id: 0, account: bob(), upheld: false }));
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Resolved);
+        assert_eq!(contract.get_accrued_fees(), 100);
+        assert_eq!(contract.claim_winnings(alice(), 0), Ok(2000));
+        assert_escrow_invariant(&contract, 100);
+    }
+
+    #[test]
+    fn test_dispute_upheld_by_arbitration_returns_bond() {
+        let mut contract = with_dispute_window();
+        resolve(&mut contract, 0, 0);
+        contract.dispute_resolution(bob(), 0, 100, 120, &mut queued(2000)).unwrap();
+
+        // The second run disagrees, so the admin decides
+        contract.take_events();
+        deliver(&mut contract, callback_origin(), resolution_callback(2000, 0, 1)).unwrap();
+        assert_eq!(contract.take_events(), vec![Event::DisputeEscalated { market_id: 0 }]);
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Disputed);
+
+        assert_eq!(contract.arbitrate_dispute(bob(), 0, Outcome::Winner(1)), Err(Error::NotAuthorized));
+        assert_eq!(contract.arbitrate_dispute(alice(), 0, Outcome::Winner(2)), Err(Error::InvalidOptionIndex));
+        contract.take_transfers();
+        assert_eq!(contract.arbitrate_dispute(alice(), 0, Outcome::Winner(1)), Ok(()));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: bob(), amount: 100 }]);
+        assert_eq!(contract.arbitrate_dispute(alice(), 0, Outcome::Winner(1)), Err(Error::MarketNotDisputed));
+
+        assert_eq!(contract.get_market(0).unwrap().winning_option, Some(1));
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(2000));
+        assert_escrow_invariant(&contract, 0);
+    }
+
+    #[test]
+    fn test_disputed_resolution_kept_after_second_round() {
+        let mut contract = with_dispute_window();
+        resolve(&mut contract, 0, 0);
+        contract.dispute_resolution(bob(), 0, 100, 120, &mut queued(2000)).unwrap();
+        let disputed = contract.get_resolution(0);
+        assert_eq!(disputed.len(), 1);
+
+        // The second run disagrees; its report joins the disputed one instead of replacing it
+        let payload = AgentCallbackPayload { run_id: 70, ..scored_callback(2000, 0, 1, 80) };
+        contract.on_resolution_complete(callback_origin(), payload, 130, &mut queued(9000)).unwrap();
+        let second = Resolution {
+            outcome: Outcome::Winner(1),
+            confidence_pct: 80,
+            evidence_summary: "Verified".into(),
+            resolver: resolver_oracle(),
+            request_id: 2000,
+            run_id: 70,
+            block: 130,
+        };
+        let out = call_as(&mut contract, charlie(), 131, &call_data(selectors::GET_RESOLUTION, 0u64));
+        let records = Vec::<Resolution>::decode(&mut &out.data[..]).unwrap();
+        assert_eq!(records, vec![disputed[0].clone(), second.clone()]);
+        assert_eq!(records[0].outcome, Outcome::Winner(0));
+        assert_eq!(records[0].request_id, 1000);
+
+        // Both stay readable once the admin settles the market
+        contract.arbitrate_dispute(alice(), 0, Outcome::Winner(1)).unwrap();
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.disputed_resolution, Some(disputed[0].clone()));
+        assert_eq!(market.resolution, Some(second));
+    }
+
+    /// Resolvers used by the quorum tests, with the default oracle first
+    fn resolvers() -> Vec<AccountId> {
+        vec![resolver_oracle(), [13u8; 32], [14u8; 32]]
+    }
+
+    /// Contract with three resolvers and a 2-of-3 quorum, and a market past its deadline
+    fn with_quorum() -> PredictionMarket {
+        let mut contract = configured();
+        contract.set_resolvers(alice(), resolvers(), 2).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 100, 100, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 100, 100, 0).unwrap();
+        contract
+    }
+
+    /// Chain extension that rejects the request sent to `resolver` and queues the rest
+    struct RejectingResolver {
+        inner: MockChainExtension,
+        resolver: AccountId,
+    }
+
+    impl ChainExtension for RejectingResolver {
+        fn call(&mut self, func_id: u32, input: &[u8]) -> (u32, Vec<u8>) {
+            if ContractAgentRequest::decode(&mut &input[..]).is_ok_and(|request| request.target_agent == self.resolver) {
+                self.inner.fail_next(ChainExtError::InsufficientFunds);
+            }
+            self.inner.call(func_id, input)
+        }
+    }
+
+    /// Extension queueing from `request_id` that rejects the second resolver's request
+    fn rejecting_second(request_id: u64) -> RejectingResolver {
+        RejectingResolver {
+            inner: queued(request_id),
+            resolver: resolvers()[1],
+        }
+    }
+
+    fn void_callback(request_id: u64, market_id: MarketId) -> AgentCallbackPayload {
+        AgentCallbackPayload {
+            request_id,
+            run_id: request_id,
+            success: true,
+            output: ResolutionResult {
+                market_id,
+                winning_option: 0,
+                void: true,
+                confidence_pct: 90,
+                evidence_summary: "Cancelled".into(),
+            }
+            .encode(),
+        }
+    }
+
+    #[test]
+    fn test_set_resolvers_validation() {
+        let mut contract = configured();
+        let r = resolvers();
+        assert_eq!(contract.set_resolvers(bob(), r.clone(), 2), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_resolvers(alice(), Vec::new(), 1), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_resolvers(alice(), vec![r[0], r[1], r[0]], 2), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_resolvers(alice(), vec![[1u8; 32]; MAX_RESOLVERS + 1], 4), Err(Error::InvalidConfig));
+        // The quorum must be a majority
+        assert_eq!(contract.set_resolvers(alice(), r.clone(), 1), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_resolvers(alice(), r.clone(), 4), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_resolvers(alice(), r[..2].to_vec(), 1), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_resolvers(alice(), r.clone(), 2), Ok(()));
+        assert_eq!(contract.config.resolver_agents, r);
+        assert_eq!(contract.config.resolver_quorum, 2);
+    }
+
+    #[test]
+    fn test_resolution_fans_out_and_settles_at_quorum() {
+        let mut contract = with_quorum();
+        let mut ext = queued(1);
+        contract.take_events();
+        let receipts = contract.request_resolution(0, 100, &mut ext).unwrap();
+        assert_eq!(receipts.iter().map(|r| r.request_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(ext.queued.iter().map(|request| request.target_agent).collect::<Vec<_>>(), resolvers());
+        assert_eq!(
+            contract.take_events(),
+            resolvers()
+                .into_iter()
+                .zip(1..)
+                .map(|(resolver, request_id)| Event::ResolutionRequested { market_id: 0, resolver, request_id })
+                .collect::<Vec<_>>()
+        );
+
+        // One answer and one failure leave the round open
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 1)).unwrap();
+        deliver(&mut contract, callback_origin(), failed_callback(2)).unwrap();
+        let pending = contract.get_pending_resolution(0).unwrap();
+        assert_eq!(pending.runs[0].status, RunStatus::Reported(Outcome::Winner(1)));
+        assert_eq!(pending.runs[1].status, RunStatus::Failed);
+        assert_eq!(pending.runs[2].status, RunStatus::Outstanding);
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::PendingResolution);
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 1)),
+            Err(Error::RequestAlreadyProcessed)
+        );
+
+        resolve_pending(&mut contract, 3, 0, 1);
+        assert_eq!(contract.get_pending_resolution(0), None);
+    }
+
+    #[test]
+    fn test_quorum_settles_before_last_resolver() {
+        let mut contract = with_quorum();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), void_callback(2, 0)).unwrap();
+        deliver(&mut contract, callback_origin(), void_callback(3, 0)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Voided);
+
+        // The late answer no longer counts
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)),
+            Err(Error::RequestIdMismatch)
+        );
+    }
+
+    #[test]
+    fn test_resolver_disagreement_escalates() {
+        let mut contract = with_quorum();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(2, 0, 1)).unwrap();
+        contract.take_events();
+
+        let mut ext = queued(9000);
+        contract.on_resolution_complete(callback_origin(), void_callback(3, 0), 100, &mut ext).unwrap();
+        assert!(ext.queued.is_empty());
+        assert_eq!(contract.take_events(), vec![Event::ResolutionEscalated { market_id: 0 }]);
+        assert_eq!(contract.get_pending_resolution(0), None);
+
+        // Nobody can re-roll the resolvers at the contract's expense
+        let input = call_data(selectors::REQUEST_RESOLUTION, 0 as MarketId);
+        let out = call_as(&mut contract, charlie(), 101, &input);
+        assert!(out.revert);
+        assert_eq!(
+            Result::<Vec<AgentRunReceipt>, Error>::decode(&mut &out.data[..]).unwrap(),
+            Err(Error::ResolutionEscalated)
+        );
+        assert!(contract.get_market(0).unwrap().escalated);
+
+        assert_eq!(contract.admin_resolve(alice(), 0, 1, 100), Ok(()));
+        assert_eq!(contract.get_market(0).unwrap().winning_option, Some(1));
+    }
+
+    #[test]
+    fn test_round_with_failures_is_re_requested() {
+        let mut contract = with_quorum();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)).unwrap();
+        deliver(&mut contract, callback_origin(), failed_callback(2)).unwrap();
+
+        // Quorum is out of reach, so the whole round is requested again
+        let mut ext = queued(10);
+        contract.on_resolution_complete(callback_origin(), failed_callback(3), 100, &mut ext).unwrap();
+        assert_eq!(ext.queued.len(), 3);
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 2);
+        assert_eq!(contract.get_pending_resolution(0).unwrap().runs.len(), 3);
+
+        deliver(&mut contract, callback_origin(), resolution_callback(10, 0, 0)).unwrap();
+        resolve_pending(&mut contract, 12, 0, 0);
+    }
+
+    #[test]
+    fn test_re_request_keeps_runs_submitted_before_a_rejection() {
+        let mut contract = with_quorum();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), failed_callback(1)).unwrap();
+
+        // The retry reaches the first and third resolvers only
+        let mut ext = rejecting_second(10);
+        contract.on_resolution_complete(callback_origin(), failed_callback(2), 100, &mut ext).unwrap();
+        assert_eq!(ext.inner.queued.len(), 2);
+        let pending = contract.get_pending_resolution(0).unwrap();
+        assert_eq!(pending.receipts().iter().map(|r| r.request_id).collect::<Vec<_>>(), vec![10, 11]);
+        assert_eq!(pending.runs.iter().map(|run| run.resolver).collect::<Vec<_>>(), vec![resolvers()[0], resolvers()[2]]);
+        assert_eq!(pending.unsubmitted, 1);
+        assert_eq!(contract.get_market(0).unwrap().resolution_attempts, 2);
+
+        // Both submitted runs are accepted and can reach quorum
+        deliver(&mut contract, callback_origin(), resolution_callback(10, 0, 1)).unwrap();
+        resolve_pending(&mut contract, 11, 0, 1);
+    }
+
+    #[test]
+    fn test_short_round_without_quorum_is_re_requested() {
+        let mut contract = with_quorum();
+        contract.request_resolution(0, 100, &mut rejecting_second(1)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)).unwrap();
+
+        // The missing resolver counts as a failure, so this is not escalated
+        let mut ext = queued(10);
+        contract.on_resolution_complete(callback_origin(), resolution_callback(2, 0, 1), 100, &mut ext).unwrap();
+        assert_eq!(ext.queued.len(), 3);
+        assert!(!contract.get_market(0).unwrap().escalated);
+        assert_eq!(contract.get_pending_resolution(0).unwrap().unsubmitted, 0);
+    }
+
+    #[test]
+    fn test_dispute_keeps_runs_submitted_before_a_rejection() {
+        let mut contract = with_quorum();
+        contract.set_dispute_window(alice(), 50, 100).unwrap();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(2, 0, 0)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Proposed);
+
+        let mut ext = rejecting_second(20);
+        contract.take_events();
+        contract.dispute_resolution(bob(), 0, 100, 120, &mut ext).unwrap();
+        assert!(!contract.take_events().contains(&Event::DisputeEscalated { market_id: 0 }));
+        assert_eq!(ext.inner.queued.len(), 2);
+        let pending = contract.get_pending_resolution(0).unwrap();
+        assert_eq!(pending.receipts().iter().map(|r| r.request_id).collect::<Vec<_>>(), vec![20, 21]);
+
+        // The second round's callbacks are accepted and settle the dispute
+        deliver(&mut contract, callback_origin(), resolution_callback(20, 0, 0)).unwrap();
+        deliver(&mut contract, callback_origin(), resolution_callback(21, 0, 0)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Resolved);
+        assert_escrow_invariant(&contract, 300);
+    }
+
+    #[test]
+    fn test_low_confidence_result_is_re_requested() {
+        let mut contract = configured();
+        contract.set_min_confidence(alice(), 50, LowConfidenceAction::ReRequest).unwrap();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        contract.take_events();
+
+        deliver(&mut contract, callback_origin(), scored_callback(1, 0, 0, 12)).unwrap();
+        assert_eq!(
+            contract.take_events(),
+            vec![
+                Event::LowConfidenceResult { market_id: 0, request_id: 1, confidence_pct: 12 },
+                Event::ResolutionRequested { market_id: 0, resolver: resolver_oracle(), request_id: 9000 },
+            ]
+        );
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.status, MarketStatus::PendingResolution);
+        assert_eq!(market.resolution, None);
+
+        deliver(&mut contract, callback_origin(), scored_callback(9000, 0, 1, 50)).unwrap();
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.winning_option, Some(1));
+        assert_eq!(market.resolution.unwrap().confidence_pct, 50);
+    }
+
+    #[test]
+    fn test_confidence_settings_fixed_for_a_round() {
+        let mut contract = with_quorum();
+        contract.set_min_confidence(alice(), 50, LowConfidenceAction::ReRequest).unwrap();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), scored_callback(1, 0, 0, 60)).unwrap();
+
+        // Runs in the same round are judged against the threshold it started with
+        contract.set_min_confidence(alice(), 90, LowConfidenceAction::Void).unwrap();
+        let pending = contract.get_pending_resolution(0).unwrap();
+        assert_eq!((pending.min_confidence_pct, pending.low_confidence_action), (50, LowConfidenceAction::ReRequest));
+        deliver(&mut contract, callback_origin(), scored_callback(2, 0, 0, 60)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().winning_option, Some(0));
+
+        // The next round picks up the new settings
+        create_binary(&mut contract);
+        contract.request_resolution(1, 100, &mut queued(10)).unwrap();
+        assert_eq!(contract.get_pending_resolution(1).unwrap().min_confidence_pct, 90);
+    }
+
+    #[test]
+    fn test_low_confidence_action() {
+        let mut contract = configured();
+        contract.set_min_confidence(alice(), 80, LowConfidenceAction::Void).unwrap();
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        deliver(&mut contract, callback_origin(), scored_callback(1, 0, 0, 12)).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Voided);
+
+        // A per-market threshold overrides the global one
+        contract.take_events();
+        contract.set_market_min_confidence(alice(), 1, Some(10)).unwrap();
+        let changed = Event::MarketMinConfidenceChanged { market_id: 1, changed_by: alice(), min_confidence_pct: Some(10) };
+        assert_eq!(changed.topics(), vec![market_topic(1), alice()]);
+        assert_eq!(contract.take_events(), vec![changed]);
+        contract.request_resolution(1, 100, &mut queued(2)).unwrap();
+        resolve_pending(&mut contract, 2, 1, 0);
+
+        contract.set_min_confidence(alice(), 80, LowConfidenceAction::Escalate).unwrap();
+        contract.request_resolution(2, 100, &mut queued(3)).unwrap();
+        contract.take_events();
+        deliver(&mut contract, callback_origin(), scored_callback(3, 2, 0, 12)).unwrap();
+        assert!(contract.take_events().contains(&Event::ResolutionEscalated { market_id: 2 }));
+
+        // Re-rolling the resolvers until one is confident enough is refused
+        let mut ext = queued(4);
+        assert_eq!(contract.request_resolution(2, 101, &mut ext), Err(Error::ResolutionEscalated));
+        assert!(ext.queued.is_empty());
+        assert_eq!(contract.get_market(2).unwrap().status, MarketStatus::PendingResolution);
+        assert_eq!(contract.admin_resolve(alice(), 2, 0, 101), Ok(()));
+    }
+
+    #[test]
+    fn test_confidence_settings_validated() {
+        let mut contract = configured();
+        assert_eq!(contract.set_min_confidence(bob(), 50, LowConfidenceAction::Void), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_min_confidence(alice(), 101, LowConfidenceAction::Void), Err(Error::InvalidConfig));
+        create_binary(&mut contract);
+        assert_eq!(contract.set_market_min_confidence(bob(), 0, Some(50)), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_market_min_confidence(alice(), 0, Some(101)), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_market_min_confidence(alice(), 1, Some(50)), Err(Error::MarketNotFound));
+
+        // Runs already counted must not be judged against a different threshold than later ones
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        assert_eq!(contract.set_market_min_confidence(alice(), 0, Some(50)), Err(Error::MarketNotOpen));
+        resolve_pending(&mut contract, 1, 0, 0);
+        assert_eq!(contract.set_market_min_confidence(alice(), 0, None), Err(Error::MarketNotOpen));
+    }
+
+    #[test]
+    fn test_bet_limits() {
+        let mut contract = configured();
+        let limits = BetLimits { min_bet: Some(10), max_bet: Some(500), max_position_per_account: Some(800) };
+        contract.set_bet_limits(alice(), limits).unwrap();
+        create_binary(&mut contract);
+
+        assert_eq!(contract.place_bet(bob(), 0, 0, 9, 9, 0), Err(Error::BetTooSmall));
+        assert_eq!(contract.place_bet(bob(), 0, 0, 501, 501, 0), Err(Error::BetTooLarge));
+        contract.place_bet(bob(), 0, 0, 500, 500, 0).unwrap();
+        // The cap counts the account's stake across every option
+        assert_eq!(contract.place_bet(bob(), 0, 1, 301, 301, 0), Err(Error::PositionLimitExceeded));
+        contract.place_bet(bob(), 0, 1, 300, 300, 0).unwrap();
+        contract.place_bet(charlie(), 0, 1, 500, 500, 0).unwrap();
+        assert_eq!(contract.get_market(0).unwrap().total_pool(), Ok(1_300));
+
+        // A per-market override replaces the global limits entirely
+        let whale_market = BetLimits { min_bet: Some(1_000), ..BetLimits::default() };
+        contract.take_events();
+        contract.set_market_bet_limits(alice(), 0, Some(whale_market)).unwrap();
+        let changed = Event::MarketBetLimitsChanged { market_id: 0, changed_by: alice(), bet_limits: Some(whale_market) };
+        assert_eq!(changed.topics(), vec![market_topic(0), alice()]);
+        assert_eq!(contract.take_events(), vec![changed]);
+        assert_eq!(contract.place_bet(charlie(), 0, 0, 999, 999, 0), Err(Error::BetTooSmall));
+        contract.place_bet(charlie(), 0, 0, 5_000, 5_000, 0).unwrap();
+
+        contract.set_market_bet_limits(alice(), 0, None).unwrap();
+        assert_eq!(contract.place_bet(charlie(), 0, 0, 100, 100, 0), Err(Error::PositionLimitExceeded));
+        assert_eq!(contract.get_market(0).unwrap().total_pool(), Ok(6_300));
+    }
+
+    #[test]
+    fn test_partial_withdrawal_respects_min_bet() {
+        let mut contract = configured();
+        let limits = BetLimits { min_bet: Some(100), ..BetLimits::default() };
+        contract.set_bet_limits(alice(), limits).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 250, 250, 0).unwrap();
+
+        // What stays on the option is held to the minimum bet, unless it is nothing
+        assert_eq!(contract.withdraw_bet(bob(), 0, 0, 151, 0), Err(Error::BetTooSmall));
+        assert_eq!(contract.withdraw_bet(bob(), 0, 0, 150, 0), Ok(150));
+        assert_eq!(contract.withdraw_bet(bob(), 0, 0, 50, 0), Err(Error::BetTooSmall));
+        assert_eq!(contract.withdraw_bet(bob(), 0, 0, 100, 0), Ok(100));
+        assert!(contract.get_position(0, bob()).is_empty());
     }
 
     #[test]
-    fn test_create_binary_market() {
-        let mut contract = PredictionMarket::new(alice());
-        contract.set_market_creator(alice(), market_creator()).unwrap();
-        
-        // Binary market with Yes/No
-        let market_id = contract.create_market(
-            market_creator(),
-            "Will BTC hit 100k?".into(),
-            vec!["Yes".into(), "No".into()],
-            "Price >= $100,000 on CoinGecko".into(),
-            "https://coingecko.com".into(),
-            100,
-        ).unwrap();
-        
-        assert_eq!(market_id, 0);
-        
-        let market = contract.get_market(0).unwrap();
-        assert_eq!(market.question, "Will BTC hit 100k?");
-        assert_eq!(market.options.len(), 2);
-        assert!(market.is_binary());
-        assert_eq!(market.status, MarketStatus::Open);
+    fn test_transfers_respect_position_limit() {
+        let mut contract = configured();
+        let limits = BetLimits { max_position_per_account: Some(800), ..BetLimits::default() };
+        contract.set_bet_limits(alice(), limits).unwrap();
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 0, 0, 500, 500, 0).unwrap();
+        contract.place_bet(charlie(), 0, 1, 500, 500, 0).unwrap();
+
+        // Stakes from several accounts cannot be pooled past the cap
+        assert_eq!(contract.transfer_position(charlie(), 0, bob(), 1, 301), Err(Error::PositionLimitExceeded));
+        contract.approve(charlie(), 0, alice(), 1, 500).unwrap();
+        assert_eq!(
+            contract.transfer_position_from(alice(), 0, charlie(), bob(), 1, 301),
+            Err(Error::PositionLimitExceeded)
+        );
+        assert_eq!(contract.transfer_position(charlie(), 0, bob(), 1, 300), Ok(()));
+        assert_eq!(contract.get_position(0, bob()).shares, vec![500, 300]);
+
+        // Once betting is over the cap no longer matters
+        resolve(&mut contract, 0, 1);
+        assert_eq!(contract.transfer_position(charlie(), 0, bob(), 1, 200), Ok(()));
+        assert_eq!(contract.get_position(0, bob()).shares, vec![500, 500]);
     }
 
     #[test]
-    fn test_create_multi_option_market() {
-        let mut contract = PredictionMarket::new(alice());
-        contract.set_market_creator(alice(), market_creator()).unwrap();
-        
-        // Multi-option market
-        let market_id = contract.create_market(
-            market_creator(),
-            "Who will win the championship?".into(),
-            vec!["Team A".into(), "Team B".into(), "Team C".into(), "Draw".into()],
-            "Official tournament results".into(),
-            "https://tournament.com".into(),
-            1000,
-        ).unwrap();
-        
-        let market = contract.get_market(market_id).unwrap();
-        assert_eq!(market.options.len(), 4);
-        assert!(!market.is_binary());
+    fn test_bet_limits_validated() {
+        let mut contract = configured();
+        let limits = |min_bet, max_bet, max_position_per_account| BetLimits { min_bet, max_bet, max_position_per_account };
+        assert_eq!(contract.set_bet_limits(bob(), limits(Some(10), None, None)), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_bet_limits(alice(), limits(Some(0), None, None)), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_bet_limits(alice(), limits(None, Some(0), None)), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_bet_limits(alice(), limits(Some(10), Some(9), None)), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_bet_limits(alice(), limits(Some(10), None, Some(9))), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_bet_limits(alice(), limits(Some(10), Some(10), Some(10))), Ok(()));
+        assert_eq!(contract.get_config().bet_limits, limits(Some(10), Some(10), Some(10)));
+
+        create_binary(&mut contract);
+        assert_eq!(contract.set_market_bet_limits(bob(), 0, None), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_market_bet_limits(alice(), 0, Some(limits(None, None, Some(0)))), Err(Error::InvalidConfig));
+        assert_eq!(contract.set_market_bet_limits(alice(), 1, None), Err(Error::MarketNotFound));
+        resolve(&mut contract, 0, 0);
+        assert_eq!(contract.set_market_bet_limits(alice(), 0, None), Err(Error::MarketNotOpen));
     }
 
     #[test]
-    fn test_place_bet_multi_option() {
-        let mut contract = PredictionMarket::new(alice());
-        contract.set_market_creator(alice(), market_creator()).unwrap();
-        
-        contract.create_market(
-            market_creator(),
-            "Test?".into(),
-            vec!["A".into(), "B".into(), "C".into()],
-            "Criteria".into(),
-            "Source".into(),
-            100,
-        ).unwrap();
-        
-        // Place bets on different options
-        contract.place_bet(alice(), 0, 0, 100).unwrap(); // Alice bets on A
-        contract.place_bet(bob(), 0, 1, 200).unwrap();   // Bob bets on B
-        contract.place_bet(charlie(), 0, 2, 150).unwrap(); // Charlie bets on C
-        
-        let market = contract.get_market(0).unwrap();
-        assert_eq!(market.shares_per_option[0], 100);
-        assert_eq!(market.shares_per_option[1], 200);
-        assert_eq!(market.shares_per_option[2], 150);
-        assert_eq!(market.total_pool(), 450);
-        
-        let alice_pos = contract.get_position(0, alice());
-        assert_eq!(alice_pos.shares, vec![100, 0, 0]);
+    fn test_resolution_record() {
+        let mut contract = configured();
+        contract.set_dispute_window(alice(), 50, 100).unwrap();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(7)).unwrap();
+        assert_eq!(contract.get_resolution(0), vec![]);
+
+        let payload = AgentCallbackPayload { run_id: 70, ..scored_callback(7, 0, 1, 88) };
+        contract.on_resolution_complete(callback_origin(), payload, 105, &mut queued(9000)).unwrap();
+
+        // Visible while the outcome can still be disputed
+        let expected = Resolution {
+            outcome: Outcome::Winner(1),
+            confidence_pct: 88,
+            evidence_summary: "Verified".into(),
+            resolver: resolver_oracle(),
+            request_id: 7,
+            run_id: 70,
+            block: 105,
+        };
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Proposed);
+        let out = call_as(&mut contract, bob(), 106, &call_data(selectors::GET_RESOLUTION, 0u64));
+        assert_eq!(Vec::<Resolution>::decode(&mut &out.data[..]).unwrap(), vec![expected.clone()]);
+        let out = call_as(&mut contract, bob(), 106, &call_data(selectors::GET_MARKET, 0u64));
+        let market = Option::<Market>::decode(&mut &out.data[..]).unwrap().unwrap();
+        assert_eq!(market.resolution, Some(expected));
+        assert_eq!(market.disputed_resolution, None);
+        assert_eq!(contract.get_resolution(1), vec![]);
     }
 
     #[test]
-    fn test_implied_odds() {
-        let mut contract = PredictionMarket::new(alice());
-        contract.set_market_creator(alice(), market_creator()).unwrap();
-        
-        contract.create_market(
-            market_creator(),
-            "Test?".into(),
-            vec!["A".into(), "B".into()],
-            "Criteria".into(),
-            "Source".into(),
-            100,
-        ).unwrap();
-        
-        // No bets - equal odds
-        let odds = contract.get_implied_odds(0).unwrap();
-        assert_eq!(odds, vec![50, 50]);
-        
-        // After bets: 75% on A, 25% on B
-        contract.place_bet(alice(), 0, 0, 300).unwrap();
-        contract.place_bet(bob(), 0, 1, 100).unwrap();
-        
-        let odds = contract.get_implied_odds(0).unwrap();
-        assert_eq!(odds, vec![75, 25]);
+    fn test_long_evidence_summary_is_truncated() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        // Far beyond what a storage entry holds; '€' is 3 bytes, so the cut lands mid-character
+        let result = ResolutionResult {
+            market_id: 0,
+            winning_option: 1,
+            void: false,
+            confidence_pct: 90,
+            evidence_summary: "€".repeat(20_000),
+        };
+        let payload = AgentCallbackPayload { output: result.encode(), ..resolution_callback(1, 0, 1) };
+        deliver(&mut contract, callback_origin(), payload).unwrap();
+
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Resolved);
+        let summary = contract.get_resolution(0).remove(0).evidence_summary;
+        assert_eq!(summary, "€".repeat(MAX_EVIDENCE_SUMMARY_LEN / 3));
+        assert_eq!(truncate_summary("short".into()), "short");
     }
 
     #[test]
-    fn test_full_lifecycle_multi_option() {
+    fn test_creator_can_cancel_empty_market() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        contract.place_bet(bob(), 1, 0, 100, 100, 0).unwrap();
+
+        assert_eq!(contract.cancel_market(bob(), 0, 0), Err(Error::NotAuthorized));
+        assert_eq!(contract.cancel_market(market_creator(), 1, 0), Err(Error::PoolNotEmpty));
+        contract.take_events();
+        assert_eq!(contract.cancel_market(market_creator(), 0, 0), Ok(()));
+        assert_eq!(
+            contract.take_events(),
+            vec![Event::MarketCancelled { market_id: 0, cancelled_by: market_creator() }]
+        );
+        assert_eq!(contract.get_market(0).unwrap().status, MarketStatus::Cancelled);
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 100, 0), Err(Error::MarketNotOpen));
+        assert_eq!(contract.cancel_market(market_creator(), 0, 0), Err(Error::MarketNotOpen));
+        assert_eq!(contract.cancel_market(alice(), 0, 0), Err(Error::MarketNotOpen));
+    }
+
+    #[test]
+    fn test_admin_cancel_refunds_positions() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 300, 300, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 200, 200, 0).unwrap();
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+
+        // An answer may still arrive while the run is inside its TTL
+        assert_eq!(contract.cancel_market(alice(), 0, 100), Err(Error::ResolutionInProgress));
+
+        // Once it has expired, cancelling drops the outstanding run
+        let expired = 100 + RESOLUTION_TTL_BLOCKS as BlockNumber;
+        assert_eq!(contract.cancel_market(alice(), 0, expired), Ok(()));
+        assert_eq!(contract.get_pending_resolution(0), None);
+        assert_eq!(
+            deliver(&mut contract, callback_origin(), resolution_callback(1, 0, 0)),
+            Err(Error::RequestIdMismatch)
+        );
+        assert_eq!(contract.request_resolution(0, 200, &mut queued(2)), Err(Error::MarketNotOpen));
+
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::Refund(300));
+        assert_eq!(contract.claim_winnings(alice(), 0), Err(Error::MarketNotResolved));
+        assert_eq!(contract.claim_refund(alice(), 0), Ok(300));
+        assert_eq!(contract.claim_refund(bob(), 0), Ok(200));
+        assert_eq!(contract.claim_refund(bob(), 0), Err(Error::NoPosition));
+        assert_escrow_invariant(&contract, 0);
+
+        create_binary(&mut contract);
+        resolve(&mut contract, 1, 0);
+        assert_eq!(contract.cancel_market(alice(), 1, 0), Err(Error::MarketNotOpen));
+    }
+
+    /// Contract with `markets` open markets, each with `bettors` positions
+    fn populated(markets: u64, bettors: u8) -> PredictionMarket {
         let mut contract = PredictionMarket::new(alice());
         contract.set_market_creator(alice(), market_creator()).unwrap();
         contract.set_resolver_oracle(alice(), resolver_oracle()).unwrap();
-        
-        // Create market with 3 options
-        let market_id = contract.create_market(
-            market_creator(),
-            "Which team wins?".into(),
-            vec!["Team A".into(), "Team B".into(), "Draw".into()],
-            "Official results".into(),
-            "tournament.com".into(),
-            100,
-        ).unwrap();
-        
-        // Place bets
-        contract.place_bet(alice(), market_id, 0, 100).unwrap();   // Team A
-        contract.place_bet(bob(), market_id, 1, 100).unwrap();     // Team B
-        contract.place_bet(charlie(), market_id, 2, 100).unwrap(); // Draw
-        
-        // Request resolution (after deadline)
-        let request = contract.request_resolution(market_id, 101).unwrap();
-        assert_eq!(request.target_agent, resolver_oracle());
-        
-        // Simulate callback (Team B wins - option index 1)
-        let result = ResolutionResult {
-            market_id,
-            winning_option: 1, // Team B
-            confidence_pct: 95,
-            evidence_summary: "Team B won 3-1".into(),
-        };
-        
-        let callback = AgentCallbackPayload {
-            request_id: 1,
-            run_id: 1,
-            success: true,
-            output: result.encode(),
+        for _ in 0..markets {
+            let id = contract.create_market(
+                market_creator(),
+                "Test?".into(),
+                vec!["A".into(), "B".into()],
+                "Criteria".into(),
+                "Source".into(),
+                100,
+                0,
+            ).unwrap();
+            for b in 0..bettors {
+                contract.place_bet([100u8.wrapping_add(b); 32], id, b % 2, 10, 10, 0).unwrap();
+            }
+        }
+        contract
+    }
+
+    #[test]
+    fn test_storage_access_independent_of_size() {
+        let mut small = populated(1, 1);
+        let mut large = populated(500, 20);
+        let last = large.next_market_id - 1;
+
+        let measure = |contract: &mut PredictionMarket, market_id: MarketId| {
+            let mut costs = Vec::new();
+            contract.storage().reset_stats();
+            contract.place_bet(bob(), market_id, 0, 10, 10, 0).unwrap();
+            costs.push(contract.storage().stats());
+            contract.storage().reset_stats();
+            contract.get_position(market_id, bob());
+            costs.push(contract.storage().stats());
+            contract.storage().reset_stats();
+            contract.get_market(market_id).unwrap();
+            costs.push(contract.storage().stats());
+            contract.storage().reset_stats();
+            contract.request_resolution(market_id, 100, &mut queued(1)).unwrap();
+            costs.push(contract.storage().stats());
+            costs
         };
-        
-        contract.on_resolution_complete(callback).unwrap();
-        
-        let market = contract.get_market(market_id).unwrap();
-        assert_eq!(market.status, MarketStatus::Resolved);
-        assert_eq!(market.winning_option, Some(1));
-        
-        // Bob (Team B) wins - gets entire pool
-        let bob_payout = contract.claim_winnings(bob(), market_id).unwrap();
-        assert_eq!(bob_payout, 300); // Gets entire pool
-        
-        // Others have no winning shares
-        assert!(contract.claim_winnings(alice(), market_id).is_err());
-        assert!(contract.claim_winnings(charlie(), market_id).is_err());
+
+        assert_eq!(measure(&mut small, 0), measure(&mut large, last));
     }
 
     #[test]
-    fn test_invalid_option_index() {
-        let mut contract = PredictionMarket::new(alice());
-        contract.set_market_creator(alice(), market_creator()).unwrap();
-        
-        contract.create_market(
+    fn test_overflowing_bet_rejected() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, Balance::MAX, Balance::MAX, 0).unwrap();
+
+        // Overflows the option's pool, then the contract's total escrow
+        assert_eq!(contract.place_bet(bob(), 0, 0, 1, 1, 0), Err(Error::Overflow));
+        assert_eq!(contract.place_bet(bob(), 1, 1, 1, 1, 0), Err(Error::Overflow));
+        assert!(contract.get_position(0, bob()).is_empty());
+        assert_eq!(contract.get_market(1).unwrap().total_pool(), Ok(0));
+        assert_escrow_invariant(&contract, Balance::MAX);
+    }
+
+    #[test]
+    fn test_winnings_with_extreme_pool() {
+        let mut contract = configured();
+        contract.set_treasury(alice(), treasury()).unwrap();
+        contract.set_protocol_fee(alice(), MAX_PROTOCOL_FEE_BPS).unwrap();
+        create_binary(&mut contract);
+        let stake = Balance::MAX / 2;
+        contract.place_bet(alice(), 0, 0, stake, stake, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, stake, stake, 0).unwrap();
+        resolve(&mut contract, 0, 0);
+
+        // winning_shares * distributable does not fit in a u128
+        let protocol_fee = stake / 10;
+        assert_eq!(contract.get_accrued_fees(), protocol_fee);
+        assert_eq!(contract.claim_winnings(alice(), 0), Ok(2 * stake - protocol_fee));
+        assert_escrow_invariant(&contract, protocol_fee);
+    }
+
+    fn extreme_balance() -> impl Strategy<Value = Balance> {
+        prop_oneof![
+            Just(1),
+            Just(Balance::MAX),
+            Just(Balance::MAX / 2),
+            Just(Balance::MAX / 3),
+            (Balance::MAX - 1_000)..=Balance::MAX,
+            1..=Balance::MAX,
+        ]
+    }
+
+    proptest! {
+        /// Bets either fit or fail with `Overflow` and change nothing, and
+        /// settlement never pays out more than the pool
+        #[test]
+        fn prop_extreme_balances_stay_in_escrow(
+            bets in prop::collection::vec((0..3usize, 0..2u8, extreme_balance()), 1..8),
+            winning_option in 0..2u8,
+            protocol_fee_bps in 0..=MAX_PROTOCOL_FEE_BPS,
+            creator_fee_bps in 0..=MAX_CREATOR_FEE_BPS,
+        ) {
+            let bettors = [alice(), bob(), charlie()];
+            let mut contract = configured();
+            contract.set_treasury(alice(), treasury()).unwrap();
+            contract.set_protocol_fee(alice(), protocol_fee_bps).unwrap();
+            contract.create_market(
+                market_creator(),
+                "Test?".into(),
+                vec!["A".into(), "B".into()],
+                "Criteria".into(),
+                "Source".into(),
+                100,
+                creator_fee_bps,
+            ).unwrap();
+
+            let mut balance: Balance = 0;
+            for (bettor, option_index, amount) in bets {
+                let before = contract.get_market(0).unwrap().shares_per_option;
+                match contract.place_bet(bettors[bettor], 0, option_index, amount, amount, 0) {
+                    Ok(()) => balance += amount,
+                    Err(err) => {
+                        prop_assert_eq!(err, Error::Overflow);
+                        prop_assert_eq!(contract.get_market(0).unwrap().shares_per_option, before);
+                    }
+                }
+                assert_escrow_invariant(&contract, balance);
+            }
+
+            let pool = contract.get_market(0).unwrap().total_pool().unwrap();
+            resolve(&mut contract, 0, winning_option);
+            assert_escrow_invariant(&contract, balance);
+
+            let mut paid: Balance = 0;
+            for bettor in bettors {
+                let claimed = contract.claim_winnings(bettor, 0).or_else(|_| contract.claim_refund(bettor, 0));
+                if let Ok(amount) = claimed {
+                    paid = paid.checked_add(amount).unwrap();
+                    balance -= amount;
+                }
+                assert_escrow_invariant(&contract, balance);
+            }
+            prop_assert!(paid <= pool);
+        }
+    }
+
+    /// Create a two-option LMSR market with liquidity 1000, funding its 694 subsidy
+    fn create_lmsr(contract: &mut PredictionMarket) -> MarketId {
+        contract.create_lmsr_market(
             market_creator(),
             "Test?".into(),
             vec!["A".into(), "B".into()],
             "Criteria".into(),
             "Source".into(),
             100,
-        ).unwrap();
-        
-        // Try to bet on non-existent option
-        assert!(contract.place_bet(alice(), 0, 5, 100).is_err());
+            1_000,
+            694,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_lmsr_trading() {
+        let mut contract = configured();
+        create_lmsr(&mut contract);
+        let mut balance: Balance = 694;
+        assert_eq!(contract.get_implied_odds(0), Some(vec![50, 50]));
+
+        assert_eq!(contract.quote(0, 0, 100), Ok(Quote { buy_cost: 51, sell_proceeds: None }));
+        assert_eq!(contract.buy_shares(alice(), 0, 0, 100, 50, 0), Err(Error::SlippageExceeded));
+        assert_eq!(contract.buy_shares(alice(), 0, 0, 100, 60, 0), Ok(51));
+        // The excess over the cost is returned
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: alice(), amount: 9 }]);
+        balance += 51;
+        assert_escrow_invariant(&contract, balance);
+
+        // Buying moves the price, so the same shares now cost more
+        let cost = contract.quote(0, 0, 100).unwrap().buy_cost;
+        assert!(cost > 51);
+        assert_eq!(contract.buy_shares(bob(), 0, 0, 100, cost, 0), Ok(cost));
+        balance += cost;
+        assert_eq!(contract.get_implied_odds(0), Some(vec![54, 45]));
+        assert_escrow_invariant(&contract, balance);
+
+        // Alice sells half of her shares at the higher price
+        assert_eq!(contract.sell_shares(alice(), 0, 0, 101, 0, 0), Err(Error::InsufficientShares));
+        assert_eq!(contract.sell_shares(charlie(), 0, 0, 1, 0, 0), Err(Error::NoPosition));
+        let proceeds = contract.quote(0, 0, 50).unwrap().sell_proceeds.unwrap();
+        assert!(proceeds > 25);
+        assert_eq!(contract.sell_shares(alice(), 0, 0, 50, proceeds + 1, 0), Err(Error::SlippageExceeded));
+        assert_eq!(contract.sell_shares(alice(), 0, 0, 50, proceeds, 0), Ok(proceeds));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: alice(), amount: proceeds }]);
+        balance -= proceeds;
+        assert_eq!(contract.get_position(0, alice()).shares, vec![50, 0]);
+        assert_escrow_invariant(&contract, balance);
+
+        // Each winning share pays 1; the market maker's remaining funds go back to the creator
+        contract.take_events();
+        resolve(&mut contract, 0, 0);
+        let returned = balance - 150;
+        assert!(contract.take_events().contains(&Event::LiquidityReturned {
+            market_id: 0,
+            creator: market_creator(),
+            amount: returned,
+        }));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: market_creator(), amount: returned }]);
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::Winnings(50));
+        assert_eq!(contract.claim_winnings(alice(), 0), Ok(50));
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(100));
+        assert_eq!(contract.get_total_escrowed(), 0);
+    }
+
+    #[test]
+    fn test_lmsr_market_creation() {
+        let mut contract = configured();
+        let create = |contract: &mut PredictionMarket, liquidity, transferred| {
+            contract.create_lmsr_market(
+                market_creator(),
+                "Test?".into(),
+                vec!["A".into(), "B".into()],
+                "Criteria".into(),
+                "Source".into(),
+                100,
+                liquidity,
+                transferred,
+            )
+        };
+        assert_eq!(create(&mut contract, 1_000, 693), Err(Error::StakeMismatch));
+        assert_eq!(create(&mut contract, 0, 0), Err(Error::ZeroAmount));
+        assert_eq!(create(&mut contract, 1_000, 700), Ok(0));
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: market_creator(), amount: 6 }]);
+        assert_eq!(contract.get_market(0).unwrap().pricing, Pricing::Lmsr { liquidity: 1_000 });
+        assert_escrow_invariant(&contract, 694);
+
+        // Through the dispatcher, with the subsidy sent as value
+        let input = call_data(
+            selectors::CREATE_LMSR_MARKET,
+            (
+                String::from("Test?"),
+                vec![String::from("A"), String::from("B")],
+                String::from("Criteria"),
+                String::from("Source"),
+                100u64,
+                1_000 as Balance,
+            ),
+        );
+        let out = pay_as(&mut contract, market_creator(), 1, 694, &input);
+        assert!(!out.revert);
+        assert_eq!(Result::<MarketId, Error>::decode(&mut &out.data[..]).unwrap(), Ok(1));
+
+        // Nobody traded, so cancelling returns the whole subsidy
+        contract.cancel_market(market_creator(), 1, 0).unwrap();
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: market_creator(), amount: 694 }]);
+        assert_escrow_invariant(&contract, 694);
+    }
+
+    #[test]
+    fn test_pricing_mode_mismatch() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        create_lmsr(&mut contract);
+
+        assert_eq!(contract.place_bet(alice(), 1, 0, 100, 100, 0), Err(Error::PricingMismatch));
+        assert_eq!(contract.buy_shares(alice(), 0, 0, 100, 100, 0), Err(Error::PricingMismatch));
+        assert_eq!(contract.sell_shares(alice(), 0, 0, 100, 0, 0), Err(Error::PricingMismatch));
+        assert_eq!(contract.quote(0, 0, 100), Err(Error::PricingMismatch));
+        assert_eq!(contract.quote(1, 2, 100), Err(Error::InvalidOptionIndex));
+        assert_eq!(contract.buy_shares(alice(), 1, 0, 0, 100, 0), Err(Error::ZeroAmount));
+        assert_eq!(contract.buy_shares(alice(), 1, 0, 100, 100, 100), Err(Error::BettingClosed));
+
+        let buy = call_data(selectors::BUY_SHARES, (1 as MarketId, 0 as OptionIndex, 100 as Balance));
+        let out = pay_as(&mut contract, alice(), 1, 51, &buy);
+        assert_eq!(Result::<Balance, Error>::decode(&mut &out.data[..]).unwrap(), Ok(51));
+        let quote = call_data(selectors::QUOTE, (1 as MarketId, 0 as OptionIndex, 100 as Balance));
+        let out = call_as(&mut contract, alice(), 1, &quote);
+        let quoted = Result::<Quote, Error>::decode(&mut &out.data[..]).unwrap().unwrap();
+        assert_eq!(quoted.sell_proceeds, Some(51));
+        let sell = call_data(selectors::SELL_SHARES, (1 as MarketId, 0 as OptionIndex, 100 as Balance, 51 as Balance));
+        let out = pay_as(&mut contract, alice(), 1, 1, &sell);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::NotPayable));
+        let out = call_as(&mut contract, alice(), 1, &sell);
+        assert_eq!(Result::<Balance, Error>::decode(&mut &out.data[..]).unwrap(), Ok(51));
+    }
+
+    #[test]
+    fn test_lmsr_void_refunds_at_final_prices() {
+        let mut contract = configured();
+        create_lmsr(&mut contract);
+        let alice_cost = contract.buy_shares(alice(), 0, 0, 300, 1_000, 0).unwrap();
+        let bob_cost = contract.buy_shares(bob(), 0, 1, 100, 1_000, 0).unwrap();
+        contract.take_transfers();
+        let mut balance = 694 + alice_cost + bob_cost;
+
+        let prices = lmsr::prices(&[300, 100], 1_000);
+        let alice_refund = math::mul_div(300, prices[0], lmsr::ONE).unwrap();
+        let bob_refund = math::mul_div(100, prices[1], lmsr::ONE).unwrap();
+        contract.void_market(alice(), 0, 0).unwrap();
+        for transfer in contract.take_transfers() {
+            assert_eq!(transfer.to, market_creator());
+            balance -= transfer.amount;
+        }
+        assert_escrow_invariant(&contract, balance);
+
+        assert_eq!(contract.get_claimable(0, alice()), Claimable::Refund(alice_refund));
+        assert_eq!(contract.claim_refund(alice(), 0), Ok(alice_refund));
+        assert_eq!(contract.claim_refund(bob(), 0), Ok(bob_refund));
+        balance -= alice_refund + bob_refund;
+        assert_escrow_invariant(&contract, balance);
+        // Only rounding dust is left behind
+        assert!(balance <= 2);
+    }
+
+    #[test]
+    fn test_withdraw_bet() {
+        let mut contract = configured();
+        assert_eq!(contract.set_exit_penalty(alice(), 500), Err(Error::TreasuryNotConfigured));
+        contract.set_treasury(alice(), treasury()).unwrap();
+        assert_eq!(contract.set_exit_penalty(bob(), 500), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_exit_penalty(alice(), MAX_EXIT_PENALTY_BPS + 1), Err(Error::FeeTooHigh));
+        contract.set_exit_penalty(alice(), 500).unwrap();
+        create_binary(&mut contract);
+        // Existing markets keep the penalty they were created with
+        contract.set_exit_penalty(alice(), 0).unwrap();
+
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.place_bet(alice(), 0, 1, 500, 500, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 1000, 1000, 0).unwrap();
+        let mut balance: Balance = 2500;
+        contract.take_events();
+
+        // 5% of the 400 withdrawn goes to the treasury
+        assert_eq!(contract.withdraw_bet(alice(), 0, 0, 400, 0), Ok(380));
+        assert_eq!(contract.take_events(), vec![Event::BetWithdrawn {
+            market_id: 0,
+            account: alice(),
+            option_index: 0,
+            amount: 400,
+            penalty: 20,
+        }]);
+        assert_eq!(contract.take_transfers(), vec![Transfer { to: alice(), amount: 380 }]);
+        balance -= 380;
+        assert_eq!(contract.get_accrued_fees(), 20);
+        assert_eq!(contract.get_position(0, alice()).shares, vec![600, 500]);
+        assert_eq!(contract.get_market(0).unwrap().shares_per_option, vec![600, 1500]);
+        assert_escrow_invariant(&contract, balance);
+
+        assert_eq!(contract.withdraw_bet(alice(), 0, 0, 601, 0), Err(Error::InsufficientShares));
+        assert_eq!(contract.withdraw_bet(charlie(), 0, 0, 1, 0), Err(Error::NoPosition));
+        assert_eq!(contract.withdraw_bet(alice(), 0, 0, 0, 0), Err(Error::ZeroAmount));
+        assert_eq!(contract.withdraw_bet(alice(), 0, 2, 1, 0), Err(Error::InvalidOptionIndex));
+        assert_eq!(contract.withdraw_bet(alice(), 0, 0, 1, 100), Err(Error::BettingClosed));
+
+        // Withdrawing everything removes the position
+        let input = call_data(selectors::WITHDRAW_BET, (0 as MarketId, 0 as OptionIndex, 600 as Balance));
+        let out = call_as(&mut contract, alice(), 0, &input);
+        assert_eq!(Result::<Balance, Error>::decode(&mut &out.data[..]).unwrap(), Ok(570));
+        assert_eq!(contract.withdraw_bet(alice(), 0, 1, 500, 0), Ok(475));
+        balance -= 570 + 475;
+        assert!(contract.get_position(0, alice()).is_empty());
+        assert_escrow_invariant(&contract, balance);
+
+        contract.request_resolution(0, 100, &mut queued(1)).unwrap();
+        assert_eq!(contract.withdraw_bet(bob(), 0, 1, 100, 0), Err(Error::MarketNotOpen));
+        resolve_pending(&mut contract, 1, 0, 1);
+        // Bob is the only bettor left on the winning side
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(1000));
+
+        create_lmsr(&mut contract);
+        assert_eq!(contract.withdraw_bet(alice(), 1, 0, 1, 0), Err(Error::PricingMismatch));
+    }
+
+    #[test]
+    fn test_transfer_position() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.place_bet(bob(), 0, 1, 1000, 1000, 0).unwrap();
+        contract.take_events();
+
+        assert_eq!(contract.transfer_position(alice(), 0, charlie(), 0, 400), Ok(()));
+        assert_eq!(contract.take_events(), vec![Event::PositionTransferred {
+            market_id: 0,
+            from: alice(),
+            to: charlie(),
+            option_index: 0,
+            amount: 400,
+        }]);
+        assert_eq!(contract.get_position(0, alice()).shares, vec![600, 0]);
+        assert_eq!(contract.get_position(0, charlie()).shares, vec![400, 0]);
+        // The pool is unchanged
+        assert_eq!(contract.get_market(0).unwrap().shares_per_option, vec![1000, 1000]);
+
+        assert_eq!(contract.transfer_position(alice(), 0, charlie(), 0, 601), Err(Error::InsufficientShares));
+        assert_eq!(contract.transfer_position(alice(), 0, charlie(), 1, 1), Err(Error::InsufficientShares));
+        assert_eq!(contract.transfer_position(alice(), 0, alice(), 0, 1), Err(Error::InvalidRecipient));
+        assert_eq!(contract.transfer_position(alice(), 0, charlie(), 2, 1), Err(Error::InvalidOptionIndex));
+        assert_eq!(contract.transfer_position(alice(), 0, charlie(), 0, 0), Err(Error::ZeroAmount));
+        assert_eq!(contract.transfer_position(treasury(), 0, charlie(), 0, 1), Err(Error::NoPosition));
+
+        // A winning ticket can be handed over after resolution, until it is claimed
+        resolve(&mut contract, 0, 0);
+        let input = call_data(selectors::TRANSFER_POSITION, (0 as MarketId, bob(), 0 as OptionIndex, 600 as Balance));
+        let out = call_as(&mut contract, alice(), 101, &input);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Ok(()));
+        assert!(contract.get_position(0, alice()).is_empty());
+        assert_eq!(contract.claim_winnings(bob(), 0), Ok(1200));
+        assert_eq!(contract.claim_winnings(charlie(), 0), Ok(800));
+        assert_eq!(contract.transfer_position(charlie(), 0, alice(), 0, 1), Err(Error::NoPosition));
+
+        // Refunded stakes follow the shares too
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 1, 1, 500, 500, 0).unwrap();
+        contract.void_market(alice(), 1, 0).unwrap();
+        contract.transfer_position(alice(), 1, bob(), 1, 200).unwrap();
+        assert_eq!(contract.claim_refund(alice(), 1), Ok(300));
+        assert_eq!(contract.claim_refund(bob(), 1), Ok(200));
+    }
+
+    #[test]
+    fn test_approved_transfer() {
+        let mut contract = configured();
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 1000, 1000, 0).unwrap();
+        contract.take_events();
+
+        assert_eq!(contract.approve(alice(), 0, bob(), 2, 100), Err(Error::InvalidOptionIndex));
+        assert_eq!(contract.approve(alice(), 0, alice(), 0, 100), Err(Error::InvalidRecipient));
+        assert_eq!(contract.get_allowance(0, alice(), alice(), 0), 0);
+        assert_eq!(contract.approve(alice(), 0, bob(), 0, 300), Ok(()));
+        assert_eq!(contract.take_events(), vec![Event::Approval {
+            market_id: 0,
+            owner: alice(),
+            spender: bob(),
+            option_index: 0,
+            amount: 300,
+        }]);
+        assert_eq!(contract.get_allowance(0, alice(), bob(), 0), 300);
+        assert_eq!(contract.get_allowance(0, alice(), bob(), 1), 0);
+
+        assert_eq!(
+            contract.transfer_position_from(charlie(), 0, alice(), charlie(), 0, 1),
+            Err(Error::InsufficientAllowance)
+        );
+        assert_eq!(
+            contract.transfer_position_from(bob(), 0, alice(), charlie(), 0, 301),
+            Err(Error::InsufficientAllowance)
+        );
+        assert_eq!(contract.transfer_position_from(bob(), 0, alice(), charlie(), 0, 200), Ok(()));
+        assert_eq!(contract.get_allowance(0, alice(), bob(), 0), 100);
+        assert_eq!(contract.get_position(0, charlie()).shares, vec![200, 0]);
+
+        // Through the dispatcher, then revoke what is left
+        let input = call_data(
+            selectors::TRANSFER_POSITION_FROM,
+            (0 as MarketId, alice(), bob(), 0 as OptionIndex, 100 as Balance),
+        );
+        let out = call_as(&mut contract, bob(), 0, &input);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Ok(()));
+        let input = call_data(selectors::GET_ALLOWANCE, (0 as MarketId, alice(), bob(), 0 as OptionIndex));
+        assert_eq!(Balance::decode(&mut &call_as(&mut contract, bob(), 0, &input).data[..]).unwrap(), 0);
+        contract.approve(alice(), 0, bob(), 0, 500).unwrap();
+        contract.approve(alice(), 0, bob(), 0, 0).unwrap();
+        assert_eq!(
+            contract.transfer_position_from(bob(), 0, alice(), bob(), 0, 1),
+            Err(Error::InsufficientAllowance)
+        );
+        assert_eq!(contract.get_position(0, alice()).shares, vec![700, 0]);
+        assert_eq!(contract.get_position(0, bob()).shares, vec![100, 0]);
+    }
+
+    #[test]
+    fn test_admin_transfer() {
+        let mut contract = configured();
+        assert_eq!(contract.propose_admin(bob(), Some(bob())), Err(Error::NotAuthorized));
+        assert_eq!(contract.accept_admin(bob()), Err(Error::NotAuthorized));
+
+        // Alice stays admin until the proposal is accepted, and can withdraw it
+        contract.propose_admin(alice(), Some(charlie())).unwrap();
+        assert_eq!(contract.get_roles().pending_admin, Some(charlie()));
+        assert_eq!(contract.set_bet_limits(charlie(), BetLimits::default()), Err(Error::NotAuthorized));
+        contract.propose_admin(alice(), None).unwrap();
+        assert_eq!(contract.accept_admin(charlie()), Err(Error::NotAuthorized));
+
+        contract.set_role(alice(), Role::Pauser, Some(charlie())).unwrap();
+        contract.propose_admin(alice(), Some(bob())).unwrap();
+        assert_eq!(contract.accept_admin(charlie()), Err(Error::NotAuthorized));
+        let out = call_as(&mut contract, bob(), 0, &selectors::ACCEPT_ADMIN);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Ok(()));
+        assert!(matches!(
+            contract.take_events().last(),
+            Some(Event::ConfigChanged { changed_by, .. }) if *changed_by == bob()
+        ));
+
+        // The previous admin's delegates do not carry over
+        let roles = contract.get_roles();
+        assert_eq!((roles.admin, roles.pending_admin, roles.pauser), (bob(), None, None));
+        assert_eq!(contract.set_paused(charlie(), true), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_treasury(alice(), treasury()), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_treasury(bob(), treasury()), Ok(()));
+        assert_eq!(contract.propose_admin(alice(), Some(alice())), Err(Error::NotAuthorized));
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut contract = configured();
+        contract.set_treasury(alice(), treasury()).unwrap();
+        assert_eq!(contract.set_role(bob(), Role::FeeManager, Some(bob())), Err(Error::NotAuthorized));
+        contract.set_role(alice(), Role::FeeManager, Some(bob())).unwrap();
+        contract.set_role(alice(), Role::Arbitrator, Some(charlie())).unwrap();
+
+        // Each role holder can use only their part of the admin's powers
+        assert_eq!(contract.set_protocol_fee(bob(), 100), Ok(()));
+        assert_eq!(contract.set_exit_penalty(bob(), 100), Ok(()));
+        assert_eq!(contract.set_dispute_window(bob(), 10, 10), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_role(bob(), Role::FeeManager, Some(charlie())), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_protocol_fee(charlie(), 0), Err(Error::NotAuthorized));
+        // The fee manager cannot redirect the fees it withdraws
+        assert_eq!(contract.set_treasury(bob(), bob()), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_paused(charlie(), true), Err(Error::NotAuthorized));
+
+        create_binary(&mut contract);
+        create_binary(&mut contract);
+        contract.place_bet(alice(), 0, 0, 100, 100, 0).unwrap();
+        assert_eq!(contract.void_market(bob(), 0, 0), Err(Error::NotAuthorized));
+        assert_eq!(contract.void_market(charlie(), 0, 0), Ok(()));
+        assert_eq!(contract.cancel_market(charlie(), 1, 0), Ok(()));
+
+        // The admin keeps every role, and roles are visible through a view
+        assert_eq!(contract.set_protocol_fee(alice(), 0), Ok(()));
+        let input = call_data(selectors::SET_ROLE, (Role::Arbitrator, None::<AccountId>));
+        let out = call_as(&mut contract, alice(), 0, &input);
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Ok(()));
+        let out = call_as(&mut contract, charlie(), 0, &selectors::GET_ROLES);
+        let roles = Roles::decode(&mut &out.data[..]).unwrap();
+        assert_eq!(
+            roles,
+            Roles {
+                admin: alice(),
+                pending_admin: None,
+                config_manager: None,
+                fee_manager: Some(bob()),
+                pauser: None,
+                arbitrator: None,
+            }
+        );
+        assert!(!contract.has_role(charlie(), Role::Arbitrator));
+        assert!(contract.has_role(alice(), Role::Arbitrator));
+    }
+
+    #[test]
+    fn test_config_manager_cannot_choose_outcomes() {
+        let mut contract = configured();
+        contract.set_role(alice(), Role::ConfigManager, Some(charlie())).unwrap();
+        assert_eq!(contract.set_market_creator(charlie(), charlie()), Ok(()));
+
+        // Either would let it decide markets without the arbitrator or the admin
+        assert_eq!(contract.set_callback_origin(charlie(), charlie()), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_resolvers(charlie(), vec![charlie()], 1), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_resolver_oracle(charlie(), charlie()), Err(Error::NotAuthorized));
+        let out = call_as(&mut contract, charlie(), 0, &call_data(selectors::SET_CALLBACK_ORIGIN, charlie()));
+        assert_eq!(Result::<(), Error>::decode(&mut &out.data[..]).unwrap(), Err(Error::NotAuthorized));
+
+        assert_eq!(contract.set_callback_origin(alice(), callback_origin()), Ok(()));
+        assert_eq!(contract.set_resolvers(alice(), vec![resolver_oracle()], 1), Ok(()));
+    }
+
+    #[test]
+    fn test_config_manager_cannot_void_by_confidence() {
+        let mut contract = configured();
+        contract.set_role(alice(), Role::ConfigManager, Some(charlie())).unwrap();
+        create_binary(&mut contract);
+
+        // The threshold is its to tune, but not what happens below it
+        assert_eq!(contract.set_min_confidence(charlie(), 60, LowConfidenceAction::ReRequest), Ok(()));
+        assert_eq!(contract.set_min_confidence(charlie(), 100, LowConfidenceAction::Void), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_min_confidence(charlie(), 60, LowConfidenceAction::Escalate), Err(Error::NotAuthorized));
+        assert_eq!(contract.get_config().low_confidence_action, LowConfidenceAction::ReRequest);
+
+        // Under Void, a higher threshold would void markets, so only the admin may move it
+        assert_eq!(contract.set_min_confidence(alice(), 60, LowConfidenceAction::Void), Ok(()));
+        assert_eq!(contract.set_min_confidence(charlie(), 100, LowConfidenceAction::Void), Err(Error::NotAuthorized));
+        assert_eq!(contract.set_market_min_confidence(charlie(), 0, Some(100)), Err(Error::NotAuthorized));
+        assert_eq!(contract.get_config().min_confidence_pct, 60);
+        assert_eq!(contract.set_market_min_confidence(alice(), 0, Some(70)), Ok(()));
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut contract = configured();
+        contract.set_role(alice(), Role::Pauser, Some(charlie())).unwrap();
+        create_binary(&mut contract);
+        let lmsr_market = create_lmsr(&mut contract);
+        contract.place_bet(bob(), 0, 0, 500, 500, 0).unwrap();
+        contract.buy_shares(bob(), lmsr_market, 0, 100, 100, 0).unwrap();
+
+        assert_eq!(contract.set_paused(bob(), true), Err(Error::NotAuthorized));
+        contract.set_paused(charlie(), true).unwrap();
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 100, 0), Err(Error::ContractPaused));
+        assert_eq!(contract.buy_shares(bob(), lmsr_market, 0, 100, 100, 0), Err(Error::ContractPaused));
+        assert_eq!(
+            contract.create_market(market_creator(), "Q?".into(), vec!["Yes".into(), "No".into()], "".into(), "".into(), 100, 0),
+            Err(Error::ContractPaused)
+        );
+
+        // Exits stay open while paused
+        assert!(contract.withdraw_bet(bob(), 0, 0, 200, 0).is_ok());
+        assert!(contract.sell_shares(bob(), lmsr_market, 0, 100, 0, 0).is_ok());
+
+        contract.set_paused(alice(), false).unwrap();
+        assert_eq!(contract.place_bet(bob(), 0, 0, 100, 100, 0), Ok(()));
     }
 }
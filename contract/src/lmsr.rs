@@ -0,0 +1,217 @@
+//! Logarithmic market scoring rule (LMSR) pricing
+//!
+//! An LMSR market maker prices every trade from the cost function
+//! `C(q) = b * ln(sum(exp(q_i / b)))`, where `q_i` is the number of shares of
+//! option `i` outstanding and `b` the liquidity parameter. A trade costs
+//! `C(after) - C(before)` and each winning share pays out 1, so the market
+//! maker can lose at most `C(0) = b * ln(n)`, which the creator funds.
+//!
+//! Everything is computed in unsigned 64.64 fixed point, without floating
+//! point. [`cost`] evaluates `max(q) + b * ln(sum(exp((q_i - max(q)) / b)))`,
+//! so every exponential is at most 1 and the logarithm's argument lies in
+//! `[1, n]`. Trades are priced as differences of the same rounded cost, so
+//! the market's escrow always equals `cost` of its outstanding shares, which
+//! covers any winning option.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{math, Balance, Error};
+
+/// 1.0 in 64.64 fixed point
+pub const ONE: u128 = 1 << 64;
+
+/// ln(2) in 64.64 fixed point
+const LN_2: u128 = 12_786_308_645_202_655_659;
+
+/// `exp(-x)` for a fixed-point `x >= 0`
+fn exp_neg(x: u128) -> u128 {
+    if x == 0 {
+        return ONE;
+    }
+    // exp(-x) = 2^-(k + 1) * exp(ln 2 - r), where x = k * ln 2 + r and 0 <= r < ln 2
+    let k = x / LN_2;
+    if k >= 127 {
+        return 0;
+    }
+    let s = LN_2 - x % LN_2;
+
+    // Taylor series of exp(s) for 0 < s <= ln 2; every term is positive
+    let mut term = ONE;
+    let mut sum = ONE;
+    let mut i = 1;
+    while term != 0 {
+        term = term * s / ONE / i;
+        sum += term;
+        i += 1;
+    }
+    sum >> (k + 1)
+}
+
+/// `ln(x)` for a fixed-point `x >= 1` (zero below that)
+fn ln(x: u128) -> u128 {
+    if x <= ONE {
+        return 0;
+    }
+    // ln(x) = k * ln 2 + ln(m), where x = 2^k * m and 1 <= m < 2
+    let k = 63 - x.leading_zeros();
+    let m = x >> k;
+
+    // ln(m) = 2 * (z + z^3 / 3 + z^5 / 5 + ...), where z = (m - 1) / (m + 1) < 1/3
+    let z = ((m - ONE) << 64) / (m + ONE);
+    let z_squared = z * z / ONE;
+    let mut power = z;
+    let mut sum = 0;
+    let mut i = 1;
+    while power != 0 {
+        sum += power / i;
+        power = power * z_squared / ONE;
+        i += 2;
+    }
+    k as u128 * LN_2 + 2 * sum
+}
+
+/// Fixed-point `exp((q_i - max(q)) / b)` for an option `gap` shares behind the leader
+fn weight(gap: Balance, liquidity: Balance) -> u128 {
+    // Gaps of 2^64 * b or more are too far behind to carry any weight
+    math::mul_div(gap, ONE, liquidity).map_or(0, exp_neg)
+}
+
+/// Cost function `C(q)` of outstanding `shares` at `liquidity`, rounded up
+pub fn cost(shares: &[Balance], liquidity: Balance) -> Result<Balance, Error> {
+    if liquidity == 0 {
+        return Err(Error::ZeroAmount);
+    }
+    let max = shares.iter().copied().max().unwrap_or_default();
+    let sum: u128 = shares.iter().map(|&q| weight(max - q, liquidity)).sum();
+    let scaled = math::mul_div_ceil(liquidity, ln(sum), ONE).ok_or(Error::Overflow)?;
+    max.checked_add(scaled).ok_or(Error::Overflow)
+}
+
+/// Subsidy a creator funds for a new market: `C(0)`, the market maker's worst-case loss
+pub fn subsidy(num_options: usize, liquidity: Balance) -> Result<Balance, Error> {
+    cost(&vec![0; num_options], liquidity)
+}
+
+/// Exact cost of buying `amount` shares of option `index`
+///
+/// Fails with [`Error::ZeroAmount`] if the trade is too small to price.
+pub fn buy_cost(shares: &[Balance], liquidity: Balance, index: usize, amount: Balance) -> Result<Balance, Error> {
+    let mut after = shares.to_vec();
+    after[index] = after[index].checked_add(amount).ok_or(Error::Overflow)?;
+    let cost = cost(&after, liquidity)?.checked_sub(cost(shares, liquidity)?);
+    cost.filter(|&cost| cost != 0).ok_or(Error::ZeroAmount)
+}
+
+/// Exact proceeds of selling `amount` shares of option `index`
+///
+/// Fails with [`Error::ZeroAmount`] if the trade is too small to price.
+pub fn sell_proceeds(shares: &[Balance], liquidity: Balance, index: usize, amount: Balance) -> Result<Balance, Error> {
+    let mut after = shares.to_vec();
+    after[index] = after[index].checked_sub(amount).ok_or(Error::InsufficientShares)?;
+    let proceeds = cost(shares, liquidity)?.checked_sub(cost(&after, liquidity)?);
+    proceeds.filter(|&proceeds| proceeds != 0).ok_or(Error::ZeroAmount)
+}
+
+/// Marginal price of each option in 64.64 fixed point
+///
+/// Rounded down, so the prices sum to at most [`ONE`].
+pub fn prices(shares: &[Balance], liquidity: Balance) -> Vec<u128> {
+    let max = shares.iter().copied().max().unwrap_or_default();
+    let weights: Vec<u128> = shares.iter().map(|&q| weight(max - q, liquidity)).collect();
+    let sum: u128 = weights.iter().sum();
+    weights.iter().map(|&w| math::mul_div(w, ONE, sum).unwrap_or_default()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn to_f64(x: u128) -> f64 {
+        x as f64 / ONE as f64
+    }
+
+    fn from_f64(x: f64) -> u128 {
+        (x * ONE as f64) as u128
+    }
+
+    #[test]
+    fn test_exp_and_ln() {
+        for x in [0.0, 1e-9, 0.3, 0.693, 1.0, 2.5, 10.0, 40.0] {
+            assert!((to_f64(exp_neg(from_f64(x))) - (-x).exp()).abs() < 1e-15, "exp(-{x})");
+        }
+        for x in [1.0, 1.000001, 1.5, 2.0, 3.7, 10.0] {
+            assert!((to_f64(ln(from_f64(x))) - x.ln()).abs() < 1e-15, "ln({x})");
+        }
+        assert_eq!(exp_neg(u128::MAX), 0);
+        assert_eq!(ln(ONE), 0);
+    }
+
+    #[test]
+    fn test_subsidy_is_b_ln_n() {
+        assert_eq!(subsidy(2, 1_000), Ok(694));
+        assert_eq!(subsidy(4, 1_000_000), Ok(1_386_295));
+        assert_eq!(subsidy(2, 0), Err(Error::ZeroAmount));
+        assert_eq!(subsidy(3, Balance::MAX), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn test_prices() {
+        let even = prices(&[0, 0], 1_000);
+        assert_eq!(even[0], even[1]);
+        assert!(ONE - (even[0] + even[1]) <= 2);
+
+        // Buying an option raises its price
+        let skewed = prices(&[1_000, 0], 1_000);
+        let expected = 1.0_f64.exp() / (1.0_f64.exp() + 1.0);
+        assert!((to_f64(skewed[0]) - expected).abs() < 1e-15);
+        assert!(skewed.iter().sum::<u128>() <= ONE);
+    }
+
+    #[test]
+    fn test_trade_costs() {
+        // 100 shares at even odds cost a little over 50
+        let cost = buy_cost(&[0, 0], 1_000, 0, 100).unwrap();
+        assert_eq!(cost, 51);
+        assert_eq!(sell_proceeds(&[100, 0], 1_000, 0, 100), Ok(cost));
+        assert_eq!(sell_proceeds(&[100, 0], 1_000, 1, 1), Err(Error::InsufficientShares));
+        assert_eq!(buy_cost(&[Balance::MAX, 0], 1_000, 0, 1), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn test_trade_too_small_to_price() {
+        // With deep liquidity one more share moves the rounded cost by nothing
+        let liquidity = 1_000_000_000_000;
+        assert_eq!(buy_cost(&[1, 0], liquidity, 0, 1), Err(Error::ZeroAmount));
+        assert_eq!(sell_proceeds(&[2, 0], liquidity, 0, 1), Err(Error::ZeroAmount));
+        assert_eq!(buy_cost(&[0, 0], liquidity, 0, 1), Ok(1));
+    }
+
+    proptest! {
+        /// The cost always covers the most-held option's payout
+        #[test]
+        fn prop_cost_covers_every_payout(shares in prop::collection::vec(any::<u64>(), 2..=10), liquidity in 1..=u64::MAX) {
+            let shares: Vec<Balance> = shares.into_iter().map(Balance::from).collect();
+            let cost = cost(&shares, liquidity as Balance).unwrap();
+            prop_assert!(cost >= *shares.iter().max().unwrap());
+        }
+
+        /// Selling what was just bought returns exactly what it cost
+        #[test]
+        fn prop_round_trip_is_exact(
+            shares in prop::collection::vec(0..1_000_000_000u128, 2..=10),
+            liquidity in 1..1_000_000_000u128,
+            index in 0..10usize,
+            amount in 1..1_000_000_000u128,
+        ) {
+            let index = index % shares.len();
+            if let Ok(cost) = buy_cost(&shares, liquidity, index, amount) {
+                let mut after = shares.clone();
+                after[index] += amount;
+                prop_assert_eq!(sell_proceeds(&after, liquidity, index, amount), Ok(cost));
+                prop_assert!(cost <= amount + 1);
+            }
+        }
+    }
+}
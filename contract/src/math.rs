@@ -0,0 +1,143 @@
+//! Overflow-safe balance arithmetic
+//!
+//! Balances are `u128`, so products of two balances (payout shares, fee
+//! rates) can exceed the type. [`mul_div`] computes them through a 256-bit
+//! intermediate and only fails when the final result does not fit.
+
+use crate::{Balance, Error};
+
+/// Full 256-bit product of `a` and `b` as `(high, low)` halves
+pub fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a0, a1) = (a & MASK, a >> 64);
+    let (b0, b1) = (b & MASK, b >> 64);
+
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+
+    // Middle column: at most three 64-bit values, so it cannot overflow
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let low = (p00 & MASK) | (mid << 64);
+    let high = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// `a * b / c` rounded down, or `None` if `c` is zero or the result overflows
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let (high, low) = widening_mul(a, b);
+    if high == 0 {
+        return Some(low / c);
+    }
+    if high >= c {
+        return None;
+    }
+
+    // Long division of the 256-bit product; the remainder stays below `c`
+    let mut remainder = high;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / c` rounded up, or `None` if `c` is zero or the result overflows
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> Option<u128> {
+    let quotient = mul_div(a, b, c)?;
+    if widening_mul(quotient, c) == widening_mul(a, b) {
+        Some(quotient)
+    } else {
+        quotient.checked_add(1)
+    }
+}
+
+/// Sum of `values`, failing with [`Error::Overflow`] if it does not fit
+pub fn checked_sum<'a>(values: impl IntoIterator<Item = &'a Balance>) -> Result<Balance, Error> {
+    values.into_iter().try_fold(0, |sum: Balance, &value| sum.checked_add(value).ok_or(Error::Overflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// 256-bit `(high, low)` difference `a - b`, assuming `a >= b`
+    fn sub_256(a: (u128, u128), b: (u128, u128)) -> (u128, u128) {
+        let (low, borrow) = a.1.overflowing_sub(b.1);
+        (a.0 - b.0 - borrow as u128, low)
+    }
+
+    #[test]
+    fn test_widening_mul() {
+        assert_eq!(widening_mul(0, u128::MAX), (0, 0));
+        assert_eq!(widening_mul(u128::MAX, 1), (0, u128::MAX));
+        assert_eq!(widening_mul(1 << 64, 1 << 64), (1, 0));
+        // (2^128 - 1)^2 = 2^256 - 2^129 + 1
+        assert_eq!(widening_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+    }
+
+    #[test]
+    fn test_mul_div_edges() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 3, 4), Some(u128::MAX / 4 * 3 + 2));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+        assert_eq!(mul_div(7, 3, 2), Some(10));
+    }
+
+    #[test]
+    fn test_mul_div_ceil() {
+        assert_eq!(mul_div_ceil(7, 3, 2), Some(11));
+        assert_eq!(mul_div_ceil(8, 3, 2), Some(12));
+        assert_eq!(mul_div_ceil(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div_ceil(u128::MAX, 3, 2), None);
+    }
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(checked_sum(&[1, 2, 3]), Ok(6));
+        assert_eq!(checked_sum(&[u128::MAX, 0]), Ok(u128::MAX));
+        assert_eq!(checked_sum(&[u128::MAX, 1]), Err(Error::Overflow));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_mul_div_is_floor_of_exact_quotient(a: u128, b: u128, c in 1..=u128::MAX) {
+            let product = widening_mul(a, b);
+            match mul_div(a, b, c) {
+                Some(quotient) => {
+                    // quotient * c <= a * b < (quotient + 1) * c
+                    let (high, low) = sub_256(product, widening_mul(quotient, c));
+                    prop_assert_eq!(high, 0);
+                    prop_assert!(low < c);
+                }
+                // Overflow only when a * b >= 2^128 * c
+                None => prop_assert!(product.0 >= c),
+            }
+        }
+
+        #[test]
+        fn prop_mul_div_matches_native_when_product_fits(a: u64, b: u64, c in 1..=u128::MAX) {
+            let (a, b) = (a as u128, b as u128);
+            prop_assert_eq!(mul_div(a, b, c), Some(a * b / c));
+        }
+
+        #[test]
+        fn prop_share_of_pool_never_exceeds_pool(pool: u128, share_num: u128, share_den in 1..=u128::MAX) {
+            let share_num = share_num % share_den;
+            let payout = mul_div(share_num, pool, share_den).unwrap();
+            prop_assert!(payout <= pool);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Contract storage abstraction
+//!
+//! Contract state is kept as individual SCALE-encoded entries under
+//! structured keys, so each call only reads and writes the markets and
+//! positions it actually touches. The [`Storage`] trait is implemented by
+//! the contract host on-chain and by [`MemoryStorage`] in tests.
+//!
+//! No contract method scans a collection: markets are addressed by
+//! `market_id` and positions by `(market_id, account)`, so the number of
+//! storage accesses per call is independent of how many markets and
+//! positions exist. [`MemoryStorage`] counts accesses so tests and
+//! benchmarks can check that.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use parity_scale_codec::{Decode, Encode};
+
+/// Key-value storage backing the contract
+pub trait Storage {
+    /// Read the raw bytes stored under `key`
+    fn get_raw(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+    /// Store raw bytes under `key`, replacing any previous value
+    fn set_raw(&mut self, key: &[u8], value: &[u8]);
+
+    /// Remove the entry under `key`
+    fn clear(&mut self, key: &[u8]);
+
+    /// Check whether anything is stored under `key`
+    fn contains(&self, key: &[u8]) -> bool {
+        self.get_raw(key).is_some()
+    }
+
+    /// Read and decode the value stored under `key`
+    fn get<T: Decode>(&self, key: &[u8]) -> Option<T> {
+        self.get_raw(key)
+            .map(|bytes| T::decode(&mut &bytes[..]).expect("corrupted storage entry"))
+    }
+
+    /// Encode and store `value` under `key`
+    fn set<T: Encode>(&mut self, key: &[u8], value: &T) {
+        self.set_raw(key, &value.encode());
+    }
+}
+
+/// Storage key layout
+///
+/// Every key is a one-byte prefix followed by the SCALE encoding of the
+/// entry's identifiers.
+pub mod keys {
+    use super::*;
+    use crate::{AccountId, MarketId, OptionIndex};
+
+    const CONFIG: u8 = 0x00;
+    const NEXT_MARKET_ID: u8 = 0x01;
+    const MARKET: u8 = 0x02;
+    const POSITION: u8 = 0x03;
+    const PENDING_RESOLUTION: u8 = 0x04;
+    const CONSUMED_REQUEST: u8 = 0x05;
+    const TOTAL_ESCROWED: u8 = 0x06;
+    const RESOLUTION_REQUEST: u8 = 0x07;
+    const ACCRUED_FEES: u8 = 0x08;
+    const CREATOR_FEES: u8 = 0x09;
+    const ALLOWANCE: u8 = 0x0a;
+
+    /// Contract configuration
+    pub fn config() -> Vec<u8> {
+        [CONFIG].to_vec()
+    }
+
+    /// Next market ID to assign
+    pub fn next_market_id() -> Vec<u8> {
+        [NEXT_MARKET_ID].to_vec()
+    }
+
+    /// Market by ID
+    pub fn market(market_id: MarketId) -> Vec<u8> {
+        (MARKET, market_id).encode()
+    }
+
+    /// Position of `account` in a market
+    pub fn position(market_id: MarketId, account: &AccountId) -> Vec<u8> {
+        (POSITION, market_id, account).encode()
+    }
+
+    /// Outstanding resolver run for a market
+    pub fn pending_resolution(market_id: MarketId) -> Vec<u8> {
+        (PENDING_RESOLUTION, market_id).encode()
+    }
+
+    /// Marker for an agent request whose callback has been processed
+    pub fn consumed_request(request_id: u64) -> Vec<u8> {
+        (CONSUMED_REQUEST, request_id).encode()
+    }
+
+    /// Market an outstanding agent request was sent for
+    pub fn resolution_request(request_id: u64) -> Vec<u8> {
+        (RESOLUTION_REQUEST, request_id).encode()
+    }
+
+    /// Funds held in escrow across all markets
+    pub fn total_escrowed() -> Vec<u8> {
+        [TOTAL_ESCROWED].to_vec()
+    }
+
+    /// Protocol fees awaiting withdrawal to the treasury
+    pub fn accrued_fees() -> Vec<u8> {
+        [ACCRUED_FEES].to_vec()
+    }
+
+    /// Creator fees awaiting withdrawal by `creator`
+    pub fn creator_fees(creator: &AccountId) -> Vec<u8> {
+        (CREATOR_FEES, creator).encode()
+    }
+
+    /// Shares of an option `spender` may transfer out of `owner`'s position
+    pub fn allowance(market_id: MarketId, owner: &AccountId, spender: &AccountId, option_index: OptionIndex) -> Vec<u8> {
+        (ALLOWANCE, market_id, owner, spender, option_index).encode()
+    }
+}
+
+/// Storage access counters, a proxy for host storage gas
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct AccessStats {
+    /// Number of `get_raw` calls
+    pub reads: u32,
+    /// Number of `set_raw` and `clear` calls
+    pub writes: u32,
+}
+
+/// In-memory storage backend for tests and off-chain simulation
+#[derive(Clone, Default, Debug)]
+pub struct MemoryStorage {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    stats: Cell<AccessStats>,
+}
+
+impl MemoryStorage {
+    /// Accesses recorded since the last [`reset_stats`](Self::reset_stats)
+    pub fn stats(&self) -> AccessStats {
+        self.stats.get()
+    }
+
+    /// Reset the access counters
+    pub fn reset_stats(&self) {
+        self.stats.set(AccessStats::default());
+    }
+
+    fn record(&self, f: impl FnOnce(&mut AccessStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if nothing is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get_raw(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.record(|stats| stats.reads += 1);
+        self.entries.get(key).cloned()
+    }
+
+    fn set_raw(&mut self, key: &[u8], value: &[u8]) {
+        self.record(|stats| stats.writes += 1);
+        self.entries.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn clear(&mut self, key: &[u8]) {
+        self.record(|stats| stats.writes += 1);
+        self.entries.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_roundtrip() {
+        let mut storage = MemoryStorage::default();
+        assert_eq!(storage.get::<u64>(&keys::next_market_id()), None);
+
+        storage.set(&keys::next_market_id(), &7u64);
+        assert_eq!(storage.get::<u64>(&keys::next_market_id()), Some(7));
+
+        storage.clear(&keys::next_market_id());
+        assert!(storage.is_empty());
+        assert_eq!(storage.stats(), AccessStats { reads: 2, writes: 2 });
+
+        storage.reset_stats();
+        assert_eq!(storage.stats(), AccessStats::default());
+    }
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let account = [1u8; 32];
+        let keys = [
+            keys::config(),
+            keys::next_market_id(),
+            keys::market(0),
+            keys::market(1),
+            keys::position(0, &account),
+            keys::position(1, &account),
+            keys::position(0, &[2u8; 32]),
+            keys::pending_resolution(0),
+            keys::consumed_request(0),
+            keys::total_escrowed(),
+            keys::resolution_request(0),
+            keys::accrued_fees(),
+            keys::creator_fees(&account),
+            keys::allowance(0, &account, &[2u8; 32], 0),
+            keys::allowance(0, &[2u8; 32], &account, 0),
+            keys::allowance(0, &account, &[2u8; 32], 1),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -110,6 +110,7 @@ echo "  3. Upload compiled agent: $AGENTS_DIR/resolver_oracle.ship"
 echo "  4. Submit and note the agent account ID"
 echo ""
 echo "  5. Call contract 'set_resolver_oracle' with the resolver agent ID"
+echo "  6. Call contract 'set_callback_origin' with the runtime's agent-callback account"
 echo ""
 
 # Summary